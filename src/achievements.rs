@@ -0,0 +1,191 @@
+use std::io;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{entity::MeteorSize, event::Event, persist};
+
+const PATH: &str = "achievements.json";
+
+/// Seconds a toast stays on screen.
+const TOAST_TIME: f32 = 3.0;
+
+/// Small meteors to destroy, over every game, for `PestControl`.
+const SMALL_METEORS: u32 = 100;
+
+/// Wave to clear without any hyperspace jump for `Grounded`.
+const GROUNDED_LEVEL: u32 = 5;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Achievement {
+    Drifter,
+    PestControl,
+    Grounded,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 3] = [
+        Achievement::Drifter,
+        Achievement::PestControl,
+        Achievement::Grounded,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::Drifter => "Drifter",
+            Achievement::PestControl => "Pest Control",
+            Achievement::Grounded => "Grounded",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::Drifter => "Clear a wave without thrusting",
+            Achievement::PestControl => "Destroy 100 small meteors",
+            Achievement::Grounded => "Survive wave 5 without hyperspace",
+        }
+    }
+}
+
+/// Achievements unlocked so far, with the progress towards the others.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Achievements {
+    unlocked: Vec<Achievement>,
+    small_meteors: u32,
+    /// A ship thrusted during the current wave.
+    #[serde(skip)]
+    thrusted: bool,
+    /// Unlocked since they were last taken, to notify the players.
+    #[serde(skip)]
+    new: Vec<Achievement>,
+}
+
+impl Achievements {
+    /// Reads the achievements, none if they were never saved.
+    pub fn load() -> Self {
+        persist::load_json(PATH)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        persist::save_json(PATH, self)
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Progress towards an achievement which is counted over every game.
+    pub fn progress(&self, achievement: Achievement) -> Option<(u32, u32)> {
+        match achievement {
+            Achievement::PestControl => {
+                Some((self.small_meteors.min(SMALL_METEORS), SMALL_METEORS))
+            }
+            Achievement::Drifter | Achievement::Grounded => None,
+        }
+    }
+
+    pub fn start_wave(&mut self) {
+        self.thrusted = false;
+    }
+
+    pub fn thrust(&mut self) {
+        self.thrusted = true;
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        if let Event::MeteorDestroyed {
+            size: MeteorSize::Small,
+            ..
+        } = event
+        {
+            self.small_meteors += 1;
+            if self.small_meteors >= SMALL_METEORS {
+                self.unlock(Achievement::PestControl);
+            }
+        }
+    }
+
+    /// Checks the achievements earned by clearing `level`, with the hyperspace
+    /// jumps made since the game started.
+    pub fn wave_cleared(&mut self, level: u32, hyperspace_jumps: u32) {
+        if !self.thrusted {
+            self.unlock(Achievement::Drifter);
+        }
+
+        if level >= GROUNDED_LEVEL && hyperspace_jumps == 0 {
+            self.unlock(Achievement::Grounded);
+        }
+    }
+
+    /// Takes the achievements unlocked since the last call.
+    pub fn take_new(&mut self) -> Vec<Achievement> {
+        std::mem::take(&mut self.new)
+    }
+
+    fn unlock(&mut self, achievement: Achievement) {
+        if !self.is_unlocked(achievement) {
+            self.unlocked.push(achievement);
+            self.new.push(achievement);
+        }
+    }
+}
+
+/// Notifications of the achievements just unlocked, shown one at a time.
+#[derive(Default)]
+pub struct Toasts {
+    queue: Vec<Achievement>,
+    /// Time the first toast of the queue has been shown.
+    time: f32,
+}
+
+impl Toasts {
+    pub fn push(&mut self, achievement: Achievement) {
+        self.queue.push(achievement);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.queue.is_empty() {
+            return;
+        }
+
+        self.time += dt;
+        if self.time >= TOAST_TIME {
+            self.queue.remove(0);
+            self.time = 0.0;
+        }
+    }
+
+    pub fn draw(&self, font: Font) {
+        let Some(achievement) = self.queue.first() else {
+            return;
+        };
+
+        let title = format!("Achievement unlocked: {}", achievement.name());
+        let dimensions = measure_text(&title, Some(font), 24, 1.0);
+        let width = dimensions.width.max(300.0) + 20.0;
+        let x = (screen_width() - width) * 0.5;
+        let y = 10.0;
+
+        draw_rectangle(x, y, width, 60.0, Color::new(0.0, 0.0, 0.0, 0.8));
+        draw_rectangle_lines(x, y, width, 60.0, 2.0, GOLD);
+
+        for (line, size, color, offset) in [
+            (title.as_str(), 24, GOLD, 26.0),
+            (achievement.description(), 18, LIGHTGRAY, 50.0),
+        ] {
+            let dimensions = measure_text(line, Some(font), size, 1.0);
+            draw_text_ex(
+                line,
+                (screen_width() - dimensions.width) * 0.5,
+                y + offset,
+                TextParams {
+                    font,
+                    font_size: size,
+                    color,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::{collections::HashMap, fs, io, rc::Rc};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::atlas::Sprite;
+
+/// Manifest of the animations, by name.
+pub const MANIFEST: &str = "res/animations.json";
+
+/// Frame of a sprite sheet.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    /// Part of the sheet, as x, y, width and height in pixels.
+    pub rect: [f32; 4],
+    /// Seconds the frame is shown.
+    pub duration: f32,
+    /// Raised to the entity when the frame shows up.
+    #[serde(default)]
+    pub event: Option<String>,
+}
+
+/// Flipbook of frames cut from one sprite sheet.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Clip {
+    /// Path of the sprite sheet.
+    pub sheet: String,
+    pub frames: Vec<Frame>,
+    /// Starts over after the last frame, else stays on it.
+    #[serde(default)]
+    pub looping: bool,
+}
+
+/// Reads the clips of the manifest.
+pub fn load_manifest() -> io::Result<HashMap<String, Clip>> {
+    let json = fs::read_to_string(MANIFEST)?;
+
+    serde_json::from_str(&json).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Clip being played.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    clip: Rc<Clip>,
+    sheet: Sprite,
+    frame: usize,
+    /// Time spent on the current frame.
+    time: f32,
+    /// The event of the first frame has been raised.
+    started: bool,
+    finished: bool,
+}
+
+impl Animation {
+    pub fn new(clip: Rc<Clip>, sheet: Sprite) -> Self {
+        Self {
+            clip,
+            sheet,
+            frame: 0,
+            time: 0.0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    pub fn sheet(&self) -> Sprite {
+        self.sheet
+    }
+
+    pub fn clip(&self) -> &Clip {
+        &self.clip
+    }
+
+    /// Moves the animation forward, returning the events of the frames that
+    /// showed up.
+    pub fn update(&mut self, dt: f32) -> Vec<String> {
+        let mut events = vec![];
+        if self.finished || self.clip.frames.is_empty() {
+            return events;
+        }
+
+        if !self.started {
+            self.started = true;
+            events.extend(self.clip.frames[0].event.clone());
+        }
+
+        self.time += dt;
+        while self.time >= self.clip.frames[self.frame].duration {
+            self.time -= self.clip.frames[self.frame].duration;
+
+            if self.frame + 1 < self.clip.frames.len() {
+                self.frame += 1;
+            } else if self.clip.looping {
+                self.frame = 0;
+            } else {
+                self.finished = true;
+                break;
+            }
+
+            if let Some(event) = self.clip.frames[self.frame].event.as_ref() {
+                events.push(event.clone());
+            }
+
+            // A frame without duration would spin forever
+            if self.clip.frames[self.frame].duration <= 0.0 {
+                break;
+            }
+        }
+
+        events
+    }
+
+    /// Back to the first frame.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.time = 0.0;
+        self.started = false;
+        self.finished = false;
+    }
+
+    /// A one-shot animation went past its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// How far the animation went, between 0.0 and 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.finished || self.clip.frames.is_empty() {
+            return 1.0;
+        }
+
+        self.frame as f32 / self.clip.frames.len() as f32
+    }
+
+    /// Part of the sheet showing the current frame, from its top left corner.
+    pub fn source(&self) -> Rect {
+        match self.clip.frames.get(self.frame) {
+            Some(frame) => Rect::new(frame.rect[0], frame.rect[1], frame.rect[2], frame.rect[3]),
+            None => Rect::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Draws the current frame centred on `position`, at its size in the
+    /// sheet unless `params` has another.
+    pub fn draw(&self, position: Vec2, color: Color, params: DrawTextureParams) {
+        self.sheet.draw(
+            position,
+            color,
+            DrawTextureParams {
+                source: Some(self.source()),
+                ..params
+            },
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Size of the playfield in virtual pixels, the same at every window size.
+pub const SIZE: Vec2 = Vec2::new(800.0, 600.0);
+
+/// How the playfield fits a window of another aspect ratio.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Aspect {
+    /// Scale it as much as it fits, with black bars on the sides left over.
+    #[default]
+    Letterbox,
+    /// Stretch it to fill the window.
+    Stretch,
+}
+
+impl Aspect {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Aspect::Letterbox => "Letterbox",
+            Aspect::Stretch => "Stretch",
+        }
+    }
+
+    pub fn next(&self) -> Aspect {
+        match self {
+            Aspect::Letterbox => Aspect::Stretch,
+            Aspect::Stretch => Aspect::Letterbox,
+        }
+    }
+}
+
+/// Maps the virtual playfield to the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arena {
+    pub size: Vec2,
+    pub aspect: Aspect,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            size: SIZE,
+            aspect: Aspect::default(),
+        }
+    }
+}
+
+impl Arena {
+    /// Part of the window showing the playfield, in screen pixels.
+    pub fn viewport(&self) -> Rect {
+        let screen = vec2(screen_width(), screen_height());
+
+        match self.aspect {
+            Aspect::Letterbox => {
+                let size = self.size * self.scale().x;
+                let origin = (screen - size) * 0.5;
+                Rect::new(origin.x, origin.y, size.x, size.y)
+            }
+            Aspect::Stretch => Rect::new(0.0, 0.0, screen.x, screen.y),
+        }
+    }
+
+    /// Screen pixels per virtual pixel on each axis.
+    pub fn scale(&self) -> Vec2 {
+        let scale = vec2(screen_width(), screen_height()) / self.size;
+
+        match self.aspect {
+            Aspect::Letterbox => Vec2::splat(scale.min_element()),
+            Aspect::Stretch => scale,
+        }
+    }
+
+    /// Camera drawing the playfield in its viewport, the whole window being
+    /// covered so that the bars can be drawn in virtual pixels too.
+    pub fn camera(&self) -> Camera2D {
+        let viewport = self.viewport();
+        let scale = self.scale();
+        let origin = -viewport.point() / scale;
+        let size = vec2(screen_width(), screen_height()) / scale;
+
+        Camera2D::from_display_rect(Rect::new(origin.x, origin.y, size.x, size.y))
+    }
+
+    /// Hides what is drawn outside the playfield, once the camera is set.
+    pub fn draw_bars(&self) {
+        let viewport = self.viewport();
+        let scale = self.scale();
+        let margin = viewport.point() / scale;
+        let (width, height) = (self.size.x, self.size.y);
+
+        if margin.x > 0.0 {
+            draw_rectangle(
+                -margin.x,
+                -margin.y,
+                margin.x,
+                height + margin.y * 2.0,
+                BLACK,
+            );
+            draw_rectangle(width, -margin.y, margin.x, height + margin.y * 2.0, BLACK);
+        }
+
+        if margin.y > 0.0 {
+            draw_rectangle(0.0, -margin.y, width, margin.y, BLACK);
+            draw_rectangle(0.0, height, width, margin.y, BLACK);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use macroquad::audio::{load_sound, Sound};
+use log::warn;
+use macroquad::audio::{load_sound, play_sound, PlaySoundParams, Sound};
 use macroquad::text::{load_ttf_font, Font};
 // use macroquad::audio::Sound;
 use macroquad::texture::{load_texture, Texture2D};
@@ -6,10 +7,22 @@ use std::collections::HashMap;
 
 use std::{cell::RefCell, rc::Rc};
 
+use crate::{
+    animation::{self, Animation, Clip},
+    atlas::{self, Atlas, Sprite},
+};
+
 pub struct AssetManager {
     sounds: HashMap<String, Rc<RefCell<Sound>>>,
     textures: HashMap<String, Rc<RefCell<Texture2D>>>,
     fonts: HashMap<String, Rc<RefCell<Font>>>,
+    /// Clips of the animation manifest, read on the first animation asked.
+    clips: Option<HashMap<String, Rc<Clip>>>,
+    /// Packed on the first sprite asked.
+    atlas: Option<Atlas>,
+    /// Nothing is sent to the GPU nor played, to run a world without a
+    /// window.
+    headless: bool,
 }
 
 impl AssetManager {
@@ -18,14 +31,53 @@ impl AssetManager {
             sounds: HashMap::new(),
             textures: HashMap::new(),
             fonts: HashMap::new(),
+            clips: None,
+            atlas: None,
+            headless: false,
+        }
+    }
+
+    /// Assets of the right sizes, but that are never drawn nor heard.
+    pub fn headless() -> Self {
+        Self {
+            headless: true,
+            ..Self::new()
         }
     }
 
+    /// Sprite of the image `name`, from the atlas unless it was left out of
+    /// it.
+    pub async fn get_sprite(&mut self, name: &str) -> Option<Sprite> {
+        if self.atlas.is_none() {
+            self.atlas = Some(if self.headless {
+                Atlas::load_headless().await
+            } else {
+                Atlas::load().await
+            });
+        }
+
+        if let Some(sprite) = self.atlas.as_ref().and_then(|atlas| atlas.get(name)) {
+            return Some(sprite);
+        }
+
+        // Drawn on its own, in a batch of its own
+        let texture = self.get_texture(name).await?;
+        let sprite = Sprite::whole(*texture.borrow());
+
+        Some(sprite)
+    }
+
     pub async fn get_texture(&mut self, name: &str) -> Option<Rc<RefCell<Texture2D>>> {
         match self.textures.get(name) {
             Some(texture) => Some((*texture).clone()),
             _ => {
-                let texture = Rc::new(RefCell::new(load_texture(name).await.unwrap()));
+                let texture = if self.headless {
+                    let image = atlas::read_image(name, true).await.unwrap();
+                    atlas::placeholder(image.width, image.height)
+                } else {
+                    load_texture(name).await.unwrap()
+                };
+                let texture = Rc::new(RefCell::new(texture));
                 let t1 = texture.clone();
                 self.textures.insert(name.to_string(), texture);
 
@@ -47,6 +99,22 @@ impl AssetManager {
         }
     }
 
+    /// Plays the sound `name` once at `volume`.
+    pub async fn play_sound(&mut self, name: &str, volume: f32) {
+        if self.headless {
+            return;
+        }
+
+        let sound = self.get_sound(name).await.unwrap();
+        play_sound(
+            *sound.borrow(),
+            PlaySoundParams {
+                looped: false,
+                volume,
+            },
+        );
+    }
+
     pub async fn get_font(&mut self, name: &str) -> Option<Rc<RefCell<Font>>> {
         match self.fonts.get(name) {
             Some(font) => Some((*font).clone()),
@@ -59,4 +127,26 @@ impl AssetManager {
             }
         }
     }
+
+    /// Starts the animation `name` of the manifest, None if it has no such
+    /// animation.
+    pub async fn get_animation(&mut self, name: &str) -> Option<Animation> {
+        if self.clips.is_none() {
+            let clips = animation::load_manifest().unwrap_or_else(|error| {
+                warn!("animations not loaded error={}", error);
+                HashMap::new()
+            });
+            self.clips = Some(
+                clips
+                    .into_iter()
+                    .map(|(name, clip)| (name, Rc::new(clip)))
+                    .collect(),
+            );
+        }
+
+        let clip = self.clips.as_ref()?.get(name)?.clone();
+        let sheet = self.get_sprite(&clip.sheet).await?;
+
+        Some(Animation::new(clip, sheet))
+    }
 }
@@ -0,0 +1,335 @@
+use std::{collections::HashMap, fs, io};
+
+use log::{debug, warn};
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::persist;
+
+/// Folders whose images are packed together.
+const FOLDERS: [&str; 5] = [
+    "res/Meteor",
+    "res/Saucer",
+    "res/Player",
+    "res/Shoot",
+    "res/Effects",
+];
+
+/// Width of the atlas in pixels, its height grows with the images.
+const WIDTH: u16 = 1024;
+
+/// Transparent pixels around each image, so filtering never bleeds one into
+/// its neighbours.
+const PADDING: u16 = 2;
+
+/// Part of a texture holding one image.
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+    pub texture: Texture2D,
+    pub source: Rect,
+}
+
+impl Sprite {
+    /// Sprite covering all of `texture`.
+    pub fn whole(texture: Texture2D) -> Self {
+        Self {
+            texture,
+            source: Rect::new(0.0, 0.0, texture.width(), texture.height()),
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.source.w
+    }
+
+    pub fn height(&self) -> f32 {
+        self.source.h
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.source.size()
+    }
+
+    /// Draws the sprite centred on `position`, at its own size unless
+    /// `params` has another. A `source` in `params` is a part of the sprite
+    /// rather than of the texture.
+    pub fn draw(&self, position: Vec2, color: Color, params: DrawTextureParams) {
+        let source = match params.source {
+            Some(rect) => Rect::new(
+                self.source.x + rect.x,
+                self.source.y + rect.y,
+                rect.w,
+                rect.h,
+            ),
+            None => self.source,
+        };
+        let size = params.dest_size.unwrap_or(source.size());
+
+        draw_texture_ex(
+            self.texture,
+            position.x - size.x / 2.0,
+            position.y - size.y / 2.0,
+            color,
+            DrawTextureParams {
+                source: Some(source),
+                dest_size: Some(size),
+                ..params
+            },
+        );
+    }
+}
+
+/// Images of the game packed in one texture, so that the scene is drawn in
+/// one batch.
+pub struct Atlas {
+    texture: Texture2D,
+    /// Part of the texture of each image, by path.
+    index: HashMap<String, Rect>,
+}
+
+impl Atlas {
+    /// Reads every image of the sprite folders and packs them, or the atlas
+    /// packed from them by an earlier run while they did not change.
+    pub async fn load() -> Self {
+        let (image, index) = Self::load_image(false).await;
+
+        Self {
+            texture: Texture2D::from_image(&image),
+            index,
+        }
+    }
+
+    /// Same layout as `load`, with nothing sent to the GPU nor written.
+    pub async fn load_headless() -> Self {
+        let (image, index) = Self::load_image(true).await;
+
+        Self {
+            texture: placeholder(image.width, image.height),
+            index,
+        }
+    }
+
+    async fn load_image(headless: bool) -> (Image, HashMap<String, Rect>) {
+        let paths = Self::paths();
+        if let Some(cached) = Cache::read(&paths) {
+            debug!("sprites read from cache count={}", cached.1.len());
+            return cached;
+        }
+
+        let (image, index) = Self::layout(Self::images(paths.clone(), headless).await);
+        if !headless {
+            if let Err(error) = Cache::write(&image, &index, paths) {
+                warn!("sprites not cached error={}", error);
+            }
+        }
+
+        (image, index)
+    }
+
+    /// Images of the sprite folders.
+    fn paths() -> Vec<String> {
+        let mut paths = vec![];
+        for folder in FOLDERS {
+            let entries = match fs::read_dir(folder) {
+                Ok(entries) => entries,
+                Err(error) => {
+                    warn!("sprites not packed folder={} error={}", folder, error);
+                    continue;
+                }
+            };
+
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name.ends_with(".png") {
+                    paths.push(format!("{}/{}", folder, name));
+                }
+            }
+        }
+        // Same layout whatever order the folders are listed in
+        paths.sort();
+
+        paths
+    }
+
+    async fn images(paths: Vec<String>, headless: bool) -> Vec<(String, Image)> {
+        let mut images = vec![];
+        for path in paths {
+            match read_image(&path, headless).await {
+                Ok(image) => images.push((path, image)),
+                Err(error) => warn!("sprite not packed path={} error={}", path, error),
+            }
+        }
+
+        images
+    }
+
+    /// Packs `images` in rows, the tallest first.
+    pub fn pack(images: Vec<(String, Image)>) -> Self {
+        let (image, index) = Self::layout(images);
+
+        Self {
+            texture: Texture2D::from_image(&image),
+            index,
+        }
+    }
+
+    /// Image of the atlas and part of it of each image.
+    fn layout(mut images: Vec<(String, Image)>) -> (Image, HashMap<String, Rect>) {
+        images.retain(|(path, image)| {
+            let fits = image.width + PADDING * 2 <= WIDTH;
+            if !fits {
+                warn!(
+                    "sprite too wide to pack path={} width={}",
+                    path, image.width
+                );
+            }
+            fits
+        });
+        images.sort_by_key(|(_, image)| std::cmp::Reverse(image.height));
+
+        let mut placements = vec![];
+        let (mut x, mut y, mut row_height) = (PADDING, PADDING, 0);
+        for (path, image) in images.iter() {
+            if x + image.width + PADDING > WIDTH && x > PADDING {
+                x = PADDING;
+                y += row_height + PADDING;
+                row_height = 0;
+            }
+
+            placements.push((path.clone(), x, y));
+            x += image.width + PADDING;
+            row_height = row_height.max(image.height);
+        }
+        let height = y + row_height + PADDING;
+
+        let mut atlas = Image::gen_image_color(WIDTH, height, Color::new(0.0, 0.0, 0.0, 0.0));
+        let mut index = HashMap::new();
+        for ((_, image), (path, x, y)) in images.iter().zip(placements) {
+            let width = image.width as usize;
+            for row in 0..image.height as usize {
+                let from = row * width * 4;
+                let to = ((y as usize + row) * WIDTH as usize + x as usize) * 4;
+                atlas.bytes[to..to + width * 4]
+                    .copy_from_slice(&image.bytes[from..from + width * 4]);
+            }
+
+            let rect = Rect::new(x as f32, y as f32, image.width as f32, image.height as f32);
+            index.insert(path, rect);
+        }
+
+        debug!(
+            "sprites packed count={} size={}x{}",
+            index.len(),
+            WIDTH,
+            height
+        );
+
+        (atlas, index)
+    }
+
+    /// Sprite of the image at `path`, if it was packed.
+    pub fn get(&self, path: &str) -> Option<Sprite> {
+        self.index.get(path).map(|source| Sprite {
+            texture: self.texture,
+            source: *source,
+        })
+    }
+
+    /// Part of the texture of each image, by path.
+    pub fn index(&self) -> &HashMap<String, Rect> {
+        &self.index
+    }
+}
+
+/// Atlas packed by an earlier run, its pixels in one file and what they hold
+/// in another.
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    width: u16,
+    height: u16,
+    /// Images the atlas was packed from, to notice one was added or removed.
+    sources: Vec<String>,
+    /// Part of the atlas of each image, as x, y, width and height.
+    index: HashMap<String, [f32; 4]>,
+}
+
+impl Cache {
+    /// Written last, so that an atlas is only read back once complete.
+    const INDEX_PATH: &'static str = "atlas.json";
+    /// Pixels of the atlas, as they are in memory.
+    const IMAGE_PATH: &'static str = "atlas.rgba";
+
+    /// Atlas packed from `sources`, None if there is none or any of them
+    /// changed since.
+    fn read(sources: &[String]) -> Option<(Image, HashMap<String, Rect>)> {
+        let cache: Cache = persist::load_json::<Option<Cache>>(Self::INDEX_PATH)?;
+        if cache.sources != sources {
+            return None;
+        }
+
+        let written = fs::metadata(Self::INDEX_PATH)
+            .and_then(|meta| meta.modified())
+            .ok()?;
+        for source in sources {
+            let modified = fs::metadata(source).and_then(|meta| meta.modified()).ok()?;
+            if modified > written {
+                return None;
+            }
+        }
+
+        let bytes = fs::read(Self::IMAGE_PATH).ok()?;
+        if bytes.len() != cache.width as usize * cache.height as usize * 4 {
+            return None;
+        }
+
+        let image = Image {
+            bytes,
+            width: cache.width,
+            height: cache.height,
+        };
+        let index = cache
+            .index
+            .into_iter()
+            .map(|(path, [x, y, w, h])| (path, Rect::new(x, y, w, h)))
+            .collect();
+
+        Some((image, index))
+    }
+
+    fn write(image: &Image, index: &HashMap<String, Rect>, sources: Vec<String>) -> io::Result<()> {
+        fs::write(Self::IMAGE_PATH, &image.bytes)?;
+
+        let cache = Cache {
+            width: image.width,
+            height: image.height,
+            sources,
+            index: index
+                .iter()
+                .map(|(path, rect)| (path.clone(), [rect.x, rect.y, rect.w, rect.h]))
+                .collect(),
+        };
+
+        persist::save_json(Self::INDEX_PATH, &cache)
+    }
+}
+
+/// Decodes the image at `path`, without the window when `headless`.
+pub async fn read_image(path: &str, headless: bool) -> Result<Image, String> {
+    if headless {
+        let bytes = fs::read(path).map_err(|error| error.to_string())?;
+        return Ok(Image::from_file_with_format(&bytes, None));
+    }
+
+    load_image(path)
+        .await
+        .map_err(|error| format!("{:?}", error))
+}
+
+/// Texture of the given size with nothing uploaded, which needs no window.
+pub fn placeholder(width: u16, height: u16) -> Texture2D {
+    let mut texture = macroquad::miniquad::Texture::empty();
+    texture.width = u32::from(width);
+    texture.height = u32::from(height);
+
+    Texture2D::from_miniquad_texture(texture)
+}
@@ -1,20 +1,35 @@
 use super::entity::Data;
 use macroquad::prelude::Vec2;
 
-pub fn circle_test(a: &Data, b: &Data) -> bool {
-    let first_rect = Vec2 {
-        x: a.texture.borrow().width(),
-        y: a.texture.borrow().height(),
-    };
-    let second_rect = Vec2 {
-        x: b.texture.borrow().width(),
-        y: b.texture.borrow().height(),
+/// Center and radius of the circle an entity collides with.
+pub fn collider(data: &Data) -> (Vec2, f32) {
+    let size = data.sprite.size();
+
+    (data.position, (size.x + size.y) / 4.0)
+}
+
+/// Shortest offset from `a` to `b` in an arena of `bounds` wrapping around
+/// its edges.
+pub fn wrapped_delta(a: Vec2, b: Vec2, bounds: Vec2) -> Vec2 {
+    let wrap = |delta: f32, bound: f32| {
+        if delta > bound / 2.0 {
+            delta - bound
+        } else if delta < -bound / 2.0 {
+            delta + bound
+        } else {
+            delta
+        }
     };
+    let delta = b - a;
+
+    Vec2::new(wrap(delta.x, bounds.x), wrap(delta.y, bounds.y))
+}
 
-    let radius_1 = (first_rect.x + first_rect.y) / 4.0;
-    let radius_2 = (second_rect.x + second_rect.y) / 4.0;
-    let xd = a.position.x - b.position.x;
-    let yd = a.position.y - b.position.y;
+/// Whether the colliders of `a` and `b` overlap, across the edges of the
+/// arena of `bounds` too.
+pub fn circle_test(a: &Data, b: &Data, bounds: Vec2) -> bool {
+    let (center_1, radius_1) = collider(a);
+    let (center_2, radius_2) = collider(b);
 
-    (xd * xd + yd * yd).sqrt() <= radius_1 + radius_2
+    wrapped_delta(center_1, center_2, bounds).length() <= radius_1 + radius_2
 }
@@ -0,0 +1,102 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Calendar date in UTC.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self::from_days((secs / 86_400) as i64)
+    }
+
+    /// Date `days` after the Unix epoch, in the proleptic Gregorian calendar.
+    fn from_days(days: i64) -> Self {
+        // Counted in eras of 400 years from March 1st of year 0, so leap days
+        // fall at the end of each year
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Seed of the challenge of this date, spread so that consecutive days
+    /// look nothing alike.
+    pub fn seed(&self) -> u64 {
+        let mut seed = (self.year as u64) * 10_000 + self.month as u64 * 100 + self.day as u64;
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        seed = (seed ^ (seed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        seed = (seed ^ (seed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        seed ^ (seed >> 31)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn counts_days_from_the_epoch() {
+        assert_eq!(Date::from_days(0), date(1970, 1, 1));
+        assert_eq!(Date::from_days(-1), date(1969, 12, 31));
+        assert_eq!(Date::from_days(365), date(1971, 1, 1));
+    }
+
+    #[test]
+    fn counts_leap_days() {
+        assert_eq!(Date::from_days(11_016), date(2000, 2, 29));
+        assert_eq!(Date::from_days(11_017), date(2000, 3, 1));
+        // Straight from February 28th, 2100 not being a leap year
+        assert_eq!(Date::from_days(47_540), date(2100, 2, 28));
+        assert_eq!(Date::from_days(47_541), date(2100, 3, 1));
+    }
+
+    #[test]
+    fn seeds_each_day_its_own_way() {
+        let day = date(2024, 2, 29);
+        assert_eq!(day.seed(), date(2024, 2, 29).seed());
+
+        let seeds: Vec<u64> = (19_000..19_400)
+            .map(|days| Date::from_days(days).seed())
+            .collect();
+        for (index, seed) in seeds.iter().enumerate() {
+            assert!(!seeds[index + 1..].contains(seed));
+        }
+    }
+}
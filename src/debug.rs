@@ -0,0 +1,200 @@
+use std::str::FromStr;
+
+use macroquad::prelude::*;
+
+use crate::{
+    collision,
+    entity::{MeteorSize, Type},
+    power_up::PowerUpKind,
+    world::World,
+};
+
+/// Lines of output kept in the console.
+const HISTORY: usize = 8;
+
+/// What the console can spawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Spawn {
+    Meteor(MeteorSize),
+    PowerUp(PowerUpKind),
+}
+
+/// Command typed in the developer console.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    /// `spawn meteor big|medium|small` or `spawn powerup <label>`
+    Spawn(Spawn),
+    /// `god`, toggles losing lives
+    God,
+    /// `level <n>`, starts the given level
+    Level(u32),
+    /// `timescale <factor>`, slows down or speeds up the game
+    TimeScale(f32),
+    /// `overlay`, toggles the debug overlay
+    Overlay,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<String> = line.split_whitespace().map(str::to_lowercase).collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+        match words.as_slice() {
+            ["spawn", "meteor", size] => {
+                let size = match *size {
+                    "big" => MeteorSize::Big,
+                    "medium" => MeteorSize::Medium,
+                    "small" => MeteorSize::Small,
+                    _ => return Err(format!("unknown meteor size '{}'", size)),
+                };
+
+                Ok(Command::Spawn(Spawn::Meteor(size)))
+            }
+            ["spawn", "powerup", label] => PowerUpKind::ALL
+                .iter()
+                .find(|kind| kind.label().eq_ignore_ascii_case(label))
+                .map(|kind| Command::Spawn(Spawn::PowerUp(*kind)))
+                .ok_or_else(|| format!("unknown power-up '{}'", label)),
+            ["god"] => Ok(Command::God),
+            ["level", level] => level
+                .parse()
+                .ok()
+                .filter(|level| *level > 0)
+                .map(Command::Level)
+                .ok_or_else(|| format!("invalid level '{}'", level)),
+            ["timescale", scale] => scale
+                .parse()
+                .ok()
+                .filter(|scale: &f32| *scale > 0.0)
+                .map(Command::TimeScale)
+                .ok_or_else(|| format!("invalid time scale '{}'", scale)),
+            ["overlay"] => Ok(Command::Overlay),
+            _ => Err(format!("unknown command '{}'", line.trim())),
+        }
+    }
+}
+
+/// Drop-down console, toggled with the key under escape.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    line: String,
+    history: Vec<String>,
+}
+
+impl Console {
+    /// Reads the keyboard, returning the command entered this frame.
+    pub fn update(&mut self) -> Option<Command> {
+        if is_key_pressed(KeyCode::GraveAccent) {
+            self.open = !self.open;
+        }
+
+        if !self.open {
+            return None;
+        }
+
+        while let Some(character) = get_char_pressed() {
+            if !character.is_control() && character != '`' {
+                self.line.push(character);
+            }
+        }
+
+        if is_key_pressed(KeyCode::Backspace) {
+            self.line.pop();
+        }
+
+        if !is_key_pressed(KeyCode::Enter) || self.line.trim().is_empty() {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.line);
+        self.print(format!("> {}", line));
+
+        match line.parse() {
+            Ok(command) => Some(command),
+            Err(error) => {
+                self.print(error);
+                None
+            }
+        }
+    }
+
+    pub fn print(&mut self, line: String) {
+        self.history.push(line);
+        if self.history.len() > HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    pub fn draw(&self) {
+        if !self.open {
+            return;
+        }
+
+        let line_height = 20.0;
+        let height = line_height * (HISTORY + 1) as f32 + 10.0;
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            height,
+            Color::new(0.0, 0.0, 0.0, 0.8),
+        );
+
+        for (i, line) in self.history.iter().enumerate() {
+            draw_text(line, 5.0, line_height * (i + 1) as f32, 20.0, LIGHTGRAY);
+        }
+
+        draw_text(
+            &format!("> {}_", self.line),
+            5.0,
+            height - 10.0,
+            20.0,
+            WHITE,
+        );
+    }
+}
+
+/// Draws the colliders, velocities and IDs of the entities, in the arena.
+pub fn draw_overlay(world: &World) {
+    for entity in world.entities.iter() {
+        let (center, radius) = collision::collider(entity.get_data());
+        let color = match entity.get_type() {
+            Type::Player(_) => GREEN,
+            Type::Shoot(_) => YELLOW,
+            Type::PowerUp => BLUE,
+            Type::Meteor(_) | Type::Enemy => RED,
+            Type::Effect => GRAY,
+        };
+
+        draw_circle_lines(center.x, center.y, radius, 1.0, color);
+
+        let velocity = center + entity.velocity() * 0.25;
+        draw_line(center.x, center.y, velocity.x, velocity.y, 1.0, color);
+
+        draw_text(
+            &entity.get_data().id().to_string(),
+            center.x + radius,
+            center.y - radius,
+            16.0,
+            color,
+        );
+    }
+}
+
+/// Draws the number of entities and the frame times, on screen.
+pub fn draw_stats(world: &World) {
+    draw_text(
+        &format!("entities: {}", world.size()),
+        screen_width() - 160.0,
+        screen_height() - 10.0,
+        20.0,
+        WHITE,
+    );
+
+    macroquad_profiler::profiler(macroquad_profiler::ProfilerParams {
+        fps_counter_pos: vec2(10.0, screen_height() - 40.0),
+    });
+}
@@ -1,69 +1,294 @@
-use std::{
-    cell::RefCell,
-    f32::consts::PI,
-    rc::Rc,
-};
+use std::{cell::RefCell, f32::consts::PI, rc::Rc};
+
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
 
 use macroquad::{
-    audio::{play_sound, play_sound_once, PlaySoundParams},
-    prelude::{is_key_down, is_key_released, rand, KeyCode, Vec2, RED, WHITE},
-    shapes::{draw_circle_lines},
-    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
-    window::{screen_height, screen_width},
+    prelude::{rand, Color, Vec2, ORANGE, WHITE},
+    shapes::draw_circle_lines,
+    text::draw_text,
+    texture::DrawTextureParams,
 };
 
-use crate::{assets_manager::AssetManager, collision::circle_test, world::World};
+use crate::{
+    animation::Animation,
+    arena,
+    assets_manager::AssetManager,
+    atlas::Sprite,
+    collision::{self, circle_test},
+    event::{DeathCause, Event},
+    hyperspace::{EscapeMode, HyperspaceConfig},
+    input::ShipInput,
+    layer::Layer,
+    physics::{self, ShipPhysics},
+    power_up::{PowerUpKind, PowerUps},
+    vector,
+    weapon::{FireMode, Weapon},
+    world::World,
+};
 
 pub struct Data {
     alive: bool,
     pub position: Vec2,
     impulse: Vec2,
-    pub texture: Rc<RefCell<Texture2D>>,
+    pub sprite: Sprite,
+    /// Path of the image, to load it back when restoring a saved entity.
+    pub asset: String,
+    /// Unique in the world, assigned when the entity is added to it.
+    id: u64,
+    /// Moved forward by the world every frame, each entity drawing it its own
+    /// way.
+    pub animation: Option<Animation>,
+}
+
+impl Data {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    fn save(&self) -> DataSave {
+        DataSave {
+            position: self.position.into(),
+            impulse: self.impulse.into(),
+            asset: self.asset.clone(),
+        }
+    }
+
+    async fn restore(assets: &Rc<RefCell<AssetManager>>, save: DataSave) -> Self {
+        let sprite = assets.borrow_mut().get_sprite(&save.asset).await.unwrap();
+
+        Self {
+            alive: true,
+            position: save.position.into(),
+            impulse: save.impulse.into(),
+            sprite,
+            asset: save.asset,
+            id: 0,
+            animation: None,
+        }
+    }
+}
+
+/// Serializable part of `Data`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataSave {
+    pub position: [f32; 2],
+    pub impulse: [f32; 2],
+    pub asset: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub slot: u8,
+    pub data: DataSave,
+    pub rotation: f32,
+    pub angular_velocity: f32,
+    pub time_since_last_shoot: f32,
+    pub last_touch: f32,
+    pub hyperspace_cooldown: f32,
+    pub jumps: u32,
+    pub vanished: Option<f32>,
+    pub doomed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShootSave {
+    pub type_: ShootType,
+    pub data: DataSave,
+    pub rotation: f32,
+    pub duration: f32,
+    pub piercing: bool,
+    pub pierce_cooldown: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MeteorSave {
+    pub size: MeteorSize,
+    pub data: DataSave,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpSave {
+    pub kind: PowerUpKind,
+    pub data: DataSave,
+    pub duration: f32,
 }
 
-#[derive(PartialEq, Clone)]
+/// Serializable state of an entity, to save a game and resume it later.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EntitySave {
+    Player(PlayerSave),
+    Shoot(ShootSave),
+    Meteor(MeteorSave),
+    PowerUp(PowerUpSave),
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum ShootType {
-    Player,
+    /// Fired by the ship of the given player.
+    Player(u8),
     Enemy,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Type {
     Meteor(u32),
-    Player,
+    /// Ship of the given player.
+    Player(u8),
     Enemy,
     Shoot(ShootType),
+    PowerUp,
+    /// Purely visual, like an explosion.
+    Effect,
 }
 
 pub trait Entity {
     fn get_type(&self) -> Type;
     fn get_data(&self) -> &Data;
+    fn get_data_mut(&mut self) -> &mut Data;
 
     fn set_position(&mut self, position: Vec2);
 
-    fn draw(&self);
+    /// Draws the entity centred on `position`, which is its own or the one of
+    /// a ghost across an edge of the arena.
+    fn draw(&self, position: Vec2);
+
+    /// Draws the entity as glowing outlines instead of its texture.
+    fn draw_vector(&self, position: Vec2) {
+        self.draw(position);
+    }
+
+    /// Layer the entity is drawn on.
+    fn layer(&self) -> Layer {
+        Layer::of(&self.get_type())
+    }
+
+    /// Order within its layer, the highest drawn on top.
+    fn z_order(&self) -> i32 {
+        0
+    }
 
     fn update(&mut self, dt: f32) -> Option<Box<dyn FnOnce(&mut World)>>;
 
-    fn is_collide(&self, _entity: &dyn Entity) -> bool {
+    /// Whether the entity is hit by `entity`, in an arena of `bounds`.
+    fn is_collide(&self, _entity: &dyn Entity, _bounds: Vec2) -> bool {
         false
     }
 
+    /// Called when a frame raising `event` shows up in the animation of the
+    /// entity.
+    fn on_animation_event(&mut self, _event: &str) {}
+
+    /// Called with the type of what hit the entity, right before `on_destroy`.
+    fn on_hit(&mut self, _by: &Type) {}
+
     fn on_destroy(&mut self) -> Option<Box<dyn FnOnce(&mut World)>> {
         None
     }
 
     fn is_alive(&self) -> bool;
+
+    /// State to write in a save file, entities returning `None` are not
+    /// saved.
+    fn save(&self) -> Option<EntitySave> {
+        None
+    }
+
+    /// Rotation in degrees, 0 pointing up.
+    fn rotation(&self) -> f32 {
+        0.0
+    }
+
+    /// Velocity in pixels per second.
+    fn velocity(&self) -> Vec2 {
+        self.get_data().impulse
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let data = self.get_data();
+
+        Snapshot {
+            id: data.id,
+            type_: self.get_type(),
+            position: data.position.into(),
+            velocity: self.velocity().into(),
+            rotation: self.rotation(),
+            size: data.sprite.size().into(),
+            asset: data.asset.clone(),
+            state: self.save(),
+        }
+    }
+}
+
+/// Plain data view of an entity, which unlike the entity itself can be
+/// printed, compared and written to JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: u64,
+    pub type_: Type,
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    pub rotation: f32,
+    pub size: [f32; 2],
+    pub asset: String,
+    /// Full state to rebuild the entity from, if it can be saved.
+    pub state: Option<EntitySave>,
+}
+
+/// Tuning of a ship, shared by every player of a game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShipConfig {
+    pub physics: ShipPhysics,
+    pub weapon: Weapon,
+    pub hyperspace: HyperspaceConfig,
+    /// Ships can shoot each other.
+    pub friendly_fire: bool,
+    /// Size of the arena the ship flies in, in virtual pixels.
+    pub arena: Vec2,
+}
+
+impl Default for ShipConfig {
+    fn default() -> Self {
+        Self {
+            physics: ShipPhysics::default(),
+            weapon: Weapon::default(),
+            hyperspace: HyperspaceConfig::default(),
+            friendly_fire: false,
+            arena: arena::SIZE,
+        }
+    }
 }
 
 pub struct Player {
     data: Data,
+    slot: u8,
+    input: Rc<RefCell<ShipInput>>,
     rotation: f32,
+    angular_velocity: f32,
+    physics: ShipPhysics,
+    weapon: Weapon,
+    friendly_fire: bool,
+    arena: Vec2,
+    shots: Rc<RefCell<u8>>,
     is_moving: bool,
     time_since_last_shoot: f32,
     asset_manager: Rc<RefCell<AssetManager>>,
     lives: Rc<RefCell<u8>>,
+    power_ups: Rc<RefCell<PowerUps>>,
     last_touch: f32,
+    hyperspace: HyperspaceConfig,
+    hyperspace_cooldown: f32,
+    jumps: u32,
+    /// Time left before reappearing while in hyperspace.
+    vanished: Option<f32>,
+    /// The current jump failed, the ship explodes when reappearing.
+    doomed: bool,
+    /// What last hit the ship.
+    hit_by: Option<Type>,
 }
 
 impl Entity for Player {
@@ -75,161 +300,301 @@ impl Entity for Player {
         &self.data
     }
 
+    fn get_data_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+
     fn get_type(&self) -> Type {
-        Type::Player
+        Type::Player(self.slot)
     }
 
     fn is_alive(&self) -> bool {
         self.data.alive
     }
 
-    fn is_collide(&self, entity: &dyn Entity) -> bool {
-        if entity.get_type() == Type::Shoot(ShootType::Player) {
+    fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    fn save(&self) -> Option<EntitySave> {
+        Some(EntitySave::Player(PlayerSave {
+            slot: self.slot,
+            data: self.data.save(),
+            rotation: self.rotation,
+            angular_velocity: self.angular_velocity,
+            time_since_last_shoot: self.time_since_last_shoot,
+            last_touch: self.last_touch,
+            hyperspace_cooldown: self.hyperspace_cooldown,
+            jumps: self.jumps,
+            vanished: self.vanished,
+            doomed: self.doomed,
+        }))
+    }
+
+    fn is_collide(&self, entity: &dyn Entity, bounds: Vec2) -> bool {
+        if self.vanished.is_some() {
             return false;
         }
 
-        circle_test(&self.data, entity.get_data())
-        // if self.get_type() == Type::Player {
-        //     // Entity is of type Player
-        //     // Do something specific for Player
-        //     true
+        match entity.get_type() {
+            Type::Shoot(ShootType::Player(owner)) if !self.friendly_fire || owner == self.slot => {
+                return false
+            }
+            Type::Player(_) | Type::PowerUp | Type::Effect => return false,
+            _ => (),
+        }
 
-        // } else {
-        //     false
-        // }
+        circle_test(&self.data, entity.get_data(), bounds)
     }
 
     fn update(&mut self, dt: f32) -> Option<Box<dyn FnOnce(&mut World)>> {
         self.time_since_last_shoot += dt;
-        // println!("time last shoot: {}", self.time_since_last_shoot);
+        self.hyperspace_cooldown -= dt;
         self.is_moving = false;
         // self.rotation = 0.0;
 
-        if is_key_down(KeyCode::Up) {
-            self.is_moving = true;
+        if *self.lives.borrow() == 0 {
+            self.data.alive = false;
+            return None;
         }
 
-        if is_key_released(KeyCode::Down) {
-            self.go_to_hyperspace();
-        }
+        if let Some(remaining) = self.vanished {
+            if remaining > 0.0 {
+                self.vanished = Some(remaining - dt);
+                return None;
+            }
 
-        if is_key_down(KeyCode::Right) {
-            self.rotation += 250.0 * dt;
-        } else if is_key_down(KeyCode::Left) {
-            self.rotation -= 250.0 * dt;
+            self.vanished = None;
+            return self.reappear();
         }
 
-        // if self.rotation != 0.0 {
-        //     let angle = self.rotation * 250 * dt;
-        //     self.rota
-        // }
+        let input = *self.input.borrow();
+        if input.thrust {
+            self.is_moving = true;
+        }
 
-        if self.is_moving {
-            let angle = self.rotation / 180.0 * PI - PI / 2.0;
-            self.data.impulse += Vec2::new(f32::cos(angle), f32::sin(angle)) * 300.0 * dt;
+        if input.escape && self.hyperspace_cooldown <= 0.0 {
+            if let Some(jump) = self.escape() {
+                return Some(jump);
+            }
         }
 
+        self.angular_velocity = self.physics.rotate(self.angular_velocity, input.turn, dt);
+        self.rotation += self.angular_velocity * dt;
+
+        self.data.impulse =
+            self.physics
+                .accelerate(self.data.impulse, self.rotation, self.is_moving, dt);
         self.data.position += dt * self.data.impulse;
 
-        // let texture_width = (*self.data.texture.borrow()).width();
-        // if self.data.position.x < -texture_width {
-        //     self.data.position.x = screen_width();
-        // } else if self.data.position.x > screen_width() {
-        //     self.data.position.x = -texture_width;
-        // }
+        self.last_touch += dt;
 
-        // let texture_height = (*self.data.texture.borrow()).height();
-        // if self.data.position.y < -texture_height {
-        //     self.data.position.y = screen_height();
-        // } else if self.data.position.y > screen_height() {
-        //     self.data.position.y = -texture_height;
-        // }
+        let fire = match self.weapon.fire_mode {
+            FireMode::Auto => input.fire,
+            FireMode::Tap => input.fire_pressed,
+        };
 
-        if is_key_down(KeyCode::Space) && self.shoot() {
+        let weapon = self.power_ups.borrow().apply(&self.weapon);
+        if fire && self.shoot(&weapon) {
             let a = self.asset_manager.clone();
             let r = self.rotation;
-            let position = self.data.position;
+            let nose = self.data.position + physics::direction(r) * self.data.sprite.height() / 2.0;
+            let velocity = self.data.impulse;
+            let shots = self.shots.clone();
+            let slot = self.slot;
 
             return Some(Box::new(move |world| {
-                world.add(Shoot::new(a, r, position, ShootType::Player))
+                for offset in weapon.spread() {
+                    world.emit(Event::ShotFired { player: slot });
+                    world.add(Shoot::new(
+                        a.clone(),
+                        ShootType::Player(slot),
+                        nose,
+                        r + offset,
+                        velocity,
+                        &weapon,
+                        shots.clone(),
+                    ))
+                }
             }));
         }
 
-        self.last_touch += dt;
-
         None
     }
 
+    fn on_hit(&mut self, by: &Type) {
+        self.hit_by = Some(*by);
+    }
+
     fn on_destroy(&mut self) -> Option<Box<dyn FnOnce(&mut World)>> {
-        if self.last_touch > 1.0 && *self.lives.borrow() > 0 {
-            // #[cfg(not(debug_assertions))]
-            // {
-            let sound = pollster::block_on(
-                self.asset_manager
-                    .borrow_mut()
-                    .get_sound("res/sounds/boom.ogg"),
-            )
-            .unwrap();
-            play_sound_once(*sound.borrow());
-            // }
-            *self.lives.borrow_mut() -= 1;
-            self.last_touch = 0.0;
+        if self.power_ups.borrow().is_active(PowerUpKind::Shield) {
+            return None;
         }
 
-        None
+        match self.hit_by {
+            Some(by) if self.last_touch > 1.0 => self.lose_life(DeathCause::Collision(by)),
+            _ => None,
+        }
     }
 
-    fn draw(&self) {
-        draw_texture_ex(
-            *self.data.texture.borrow(),
-            self.data.position.x - ((*self.data.texture.borrow()).width() / 2.0),
-            self.data.position.y - ((*self.data.texture.borrow()).height() / 2.0),
+    fn draw(&self, position: Vec2) {
+        if self.vanished.is_some() {
+            return;
+        }
+
+        if let (true, Some(flame)) = (self.is_moving, self.data.animation.as_ref()) {
+            // Tucked under the back of the ship
+            let behind = (self.data.sprite.height() + flame.source().h) / 2.0 - 4.0;
+            let center = position - physics::direction(self.rotation) * behind;
+            flame.draw(
+                center,
+                WHITE,
+                DrawTextureParams {
+                    rotation: self.rotation * PI / 180.0,
+                    pivot: Some(center),
+                    ..Default::default()
+                },
+            );
+        }
+
+        self.data.sprite.draw(
+            position,
             WHITE,
             DrawTextureParams {
                 rotation: self.rotation * PI / 180.0,
-                pivot: Some(Vec2 {
-                    x: self.data.position.x,
-                    y: self.data.position.y,
-                }),
+                pivot: Some(position),
                 ..Default::default()
             },
         );
         // draw_texture(self.data.texture, self.data.rect.x, self.data.rect.y, WHITE);
+
+        if self.power_ups.borrow().is_active(PowerUpKind::Shield) {
+            draw_circle_lines(
+                position.x,
+                position.y,
+                self.data.sprite.size().max_element() * 0.75,
+                2.0,
+                PowerUpKind::Shield.color(),
+            );
+        }
+    }
+
+    fn draw_vector(&self, position: Vec2) {
+        if self.vanished.is_some() {
+            return;
+        }
+
+        let size = self.data.sprite.size();
+        let ship = vector::transform(&vector::SHIP, size, self.rotation, position);
+        vector::draw_outline(&ship, true, WHITE);
+
+        if self.is_moving {
+            let flame = vector::transform(&vector::FLAME, size, self.rotation, position);
+            vector::draw_outline(&flame, false, ORANGE);
+        }
+
+        if self.power_ups.borrow().is_active(PowerUpKind::Shield) {
+            vector::draw_circle_outline(
+                position,
+                size.max_element() * 0.75,
+                PowerUpKind::Shield.color(),
+            );
+        }
     }
 }
 
 impl Player {
-    pub async fn new(asset_manager: Rc<RefCell<AssetManager>>, lives: Rc<RefCell<u8>>) -> Self {
-        let texture = asset_manager
+    /// Creates the ship of player `slot`, driven by `input` and sharing its
+    /// `lives` and `power_ups` with the game.
+    pub async fn new(
+        asset_manager: Rc<RefCell<AssetManager>>,
+        slot: u8,
+        input: Rc<RefCell<ShipInput>>,
+        lives: Rc<RefCell<u8>>,
+        power_ups: Rc<RefCell<PowerUps>>,
+        config: &ShipConfig,
+    ) -> Self {
+        let asset = "res/Player/Ship.png";
+        let sprite = asset_manager.borrow_mut().get_sprite(asset).await.unwrap();
+        let flame = asset_manager
             .borrow_mut()
-            .get_texture("res/Player/Ship.png")
-            .await
-            .unwrap();
+            .get_animation("ship_thrust")
+            .await;
 
-        let position = Vec2 {
-            x: screen_width() / 2.0,
-            y: screen_height() / 2.0,
-        };
+        let position = config.arena / 2.0;
 
         Self {
+            slot,
+            input,
             lives,
+            power_ups,
             data: Data {
                 alive: true,
                 position,
                 impulse: Vec2::default(),
-                texture,
+                sprite,
+                asset: asset.to_string(),
+                id: 0,
+                animation: flame,
             },
             // entity: Entity::new(texture),
             rotation: 0.0,
+            angular_velocity: 0.0,
+            physics: config.physics,
+            weapon: config.weapon,
+            friendly_fire: config.friendly_fire,
+            arena: config.arena,
+            shots: Rc::new(RefCell::new(0)),
             is_moving: false,
             time_since_last_shoot: 0.0,
             asset_manager,
             last_touch: 0.0,
+            hyperspace: config.hyperspace,
+            hyperspace_cooldown: 0.0,
+            jumps: 0,
+            vanished: None,
+            doomed: false,
+            hit_by: None,
         }
     }
 
-    fn shoot(&mut self) -> bool {
-        if self.time_since_last_shoot > 0.5 {
+    /// Recreates a saved ship, sharing its `input`, `lives` and `power_ups`
+    /// with the game like a new one.
+    pub async fn restore(
+        asset_manager: Rc<RefCell<AssetManager>>,
+        input: Rc<RefCell<ShipInput>>,
+        lives: Rc<RefCell<u8>>,
+        power_ups: Rc<RefCell<PowerUps>>,
+        config: &ShipConfig,
+        save: PlayerSave,
+    ) -> Self {
+        let data = Data::restore(&asset_manager, save.data).await;
+        let mut player =
+            Player::new(asset_manager, save.slot, input, lives, power_ups, config).await;
+
+        let flame = player.data.animation.take();
+        player.data = data;
+        player.data.animation = flame;
+        player.rotation = save.rotation;
+        player.angular_velocity = save.angular_velocity;
+        player.time_since_last_shoot = save.time_since_last_shoot;
+        player.last_touch = save.last_touch;
+        player.hyperspace_cooldown = save.hyperspace_cooldown;
+        player.jumps = save.jumps;
+        player.vanished = save.vanished;
+        player.doomed = save.doomed;
+
+        player
+    }
+
+    /// Counter of the shots of the ship currently on screen, to restore them.
+    pub fn shots(&self) -> Rc<RefCell<u8>> {
+        self.shots.clone()
+    }
+
+    fn shoot(&mut self, weapon: &Weapon) -> bool {
+        if self.time_since_last_shoot > weapon.cooldown() && *self.shots.borrow() < weapon.max_shots
+        {
             self.time_since_last_shoot = 0.0;
             return true;
         }
@@ -237,22 +602,98 @@ impl Player {
         false
     }
 
+    fn lose_life(&mut self, cause: DeathCause) -> Option<Box<dyn FnOnce(&mut World)>> {
+        if *self.lives.borrow() == 0 {
+            return None;
+        }
+
+        pollster::block_on(
+            self.asset_manager
+                .borrow_mut()
+                .play_sound("res/sounds/boom.ogg", 1.0),
+        );
+        *self.lives.borrow_mut() -= 1;
+        self.last_touch = 0.0;
+
+        let player = self.slot;
+        let assets = self.asset_manager.clone();
+        let position = self.data.position;
+        let size = self.data.sprite.width() * 2.0;
+        Some(Box::new(move |world| {
+            world.emit(Event::ShipDestroyed { player, cause });
+            if let Some(explosion) = Explosion::new(assets, position, size) {
+                world.add(explosion);
+            }
+        }))
+    }
+
+    /// Jumps to hyperspace or raises the shield, returning what tells the
+    /// world about the jump.
+    fn escape(&mut self) -> Option<Box<dyn FnOnce(&mut World)>> {
+        self.hyperspace_cooldown = self.hyperspace.cooldown();
+
+        match self.hyperspace.mode {
+            EscapeMode::Hyperspace => {
+                self.go_to_hyperspace();
+
+                let player = self.slot;
+                Some(Box::new(move |world| {
+                    world.emit(Event::HyperspaceJump { player })
+                }))
+            }
+            EscapeMode::Shield => {
+                self.power_ups
+                    .borrow_mut()
+                    .grant(PowerUpKind::Shield, self.hyperspace.shield_duration);
+
+                None
+            }
+        }
+    }
+
     pub fn go_to_hyperspace(&mut self) {
+        self.doomed = rand::gen_range(0.0, 1.0) < self.hyperspace.failure_chance(self.jumps);
+        self.jumps += 1;
+        self.vanished = Some(self.hyperspace.vanish_time);
         self.data.impulse = Vec2::splat(0.0);
+        self.angular_velocity = 0.0;
+        let arena = self.arena;
         self.data.position = Vec2 {
-            x: rand::gen_range(0.0, screen_width() - (*self.data.texture.borrow()).width()),
-            y: rand::gen_range(
-                0.0,
-                screen_height() - (*self.data.texture.borrow()).height(),
-            ),
+            x: rand::gen_range(0.0, arena.x),
+            y: rand::gen_range(0.0, arena.y),
         };
-        let sound = pollster::block_on(
+        pollster::block_on(
             self.asset_manager
                 .borrow_mut()
-                .get_sound("res/sounds/hyperspace.ogg"),
-        )
-        .unwrap();
-        play_sound_once(*sound.borrow());
+                .play_sound("res/sounds/hyperspace.ogg", 1.0),
+        );
+    }
+
+    fn reappear(&mut self) -> Option<Box<dyn FnOnce(&mut World)>> {
+        if self.doomed {
+            self.doomed = false;
+
+            return self.lose_life(DeathCause::Hyperspace);
+        }
+
+        if !self.hyperspace.safe_landing {
+            return None;
+        }
+
+        let size = self.data.sprite.size();
+        let radius = self.hyperspace.safe_radius;
+        let slot = self.slot;
+
+        Some(Box::new(move |world| {
+            let position = world.find_clear_position(size, radius);
+            if let Some(player) = world
+                .entities
+                .iter_mut()
+                .find(|entity| entity.get_type() == Type::Player(slot))
+            {
+                player.set_position(position);
+            }
+        }))
     }
 }
 
@@ -261,6 +702,11 @@ pub struct Shoot {
     data: Data,
     rotation: f32,
     type_: ShootType,
+    shots: Rc<RefCell<u8>>,
+    piercing: bool,
+    /// Time left before a piercing shot can hit again, so it does not
+    /// destroy the fragments of the meteor it just went through.
+    pierce_cooldown: f32,
 }
 
 impl Entity for Shoot {
@@ -268,145 +714,219 @@ impl Entity for Shoot {
         self.data.position = position;
     }
 
-    fn is_collide(&self, entity: &dyn Entity) -> bool {
-        if let Type::Enemy | Type::Meteor(_) = entity.get_type() {
-            return circle_test(&self.data, entity.get_data());
+    fn is_collide(&self, entity: &dyn Entity, bounds: Vec2) -> bool {
+        if self.pierce_cooldown > 0.0 {
+            return false;
         }
 
-        false
+        match entity.get_type() {
+            Type::Enemy | Type::Meteor(_) => circle_test(&self.data, entity.get_data(), bounds),
+            // The ship knows whether friendly fire is on and whether it is
+            // there at all, the shot dies on the ships it hits
+            Type::Player(_) => entity.is_collide(self, bounds),
+            _ => false,
+        }
     }
 
     fn get_data(&self) -> &Data {
         &self.data
     }
 
+    fn get_data_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+
     fn get_type(&self) -> Type {
-        Type::Shoot(self.type_.clone())
+        Type::Shoot(self.type_)
     }
 
     fn is_alive(&self) -> bool {
         self.data.alive
     }
 
+    fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    fn save(&self) -> Option<EntitySave> {
+        Some(EntitySave::Shoot(ShootSave {
+            type_: self.type_,
+            data: self.data.save(),
+            rotation: self.rotation,
+            duration: self.duration,
+            piercing: self.piercing,
+            pierce_cooldown: self.pierce_cooldown,
+        }))
+    }
+
     fn update(&mut self, dt: f32) -> Option<Box<dyn FnOnce(&mut World)>> {
         // if self.duration < 0.0 {
         //     self.data.alive = false
         // }
         self.data.position += dt * self.data.impulse;
         self.duration -= dt;
+        self.pierce_cooldown -= dt;
 
         if self.duration < 0.0 {
-            self.data.alive = false;
+            self.kill();
         }
 
         None
     }
 
     fn on_destroy(&mut self) -> Option<Box<dyn FnOnce(&mut World)>> {
-        self.data.alive = false;
-        println!("Desotry shoot");
+        if self.piercing {
+            self.pierce_cooldown = 0.2;
+            return None;
+        }
+
+        self.kill();
+        trace!("shot destroyed type={:?}", self.type_);
 
         None
     }
 
-    fn draw(&self) {
-        let texture = self.data.texture.borrow();
-        let pivot = Some(Vec2 {
-            x: self.data.position.x + ((*texture).width() / 2.0),
-            y: self.data.position.y + ((*texture).height() / 2.0),
-        });
-
-        draw_texture_ex(
-            *texture,
-            self.data.position.x,
-            self.data.position.y,
+    fn draw(&self, position: Vec2) {
+        self.data.sprite.draw(
+            position,
             WHITE,
             DrawTextureParams {
                 rotation: self.rotation * PI / 180.0,
-                pivot,
+                pivot: Some(position),
                 ..Default::default()
             },
         );
 
-        let texture = *self.data.texture.borrow();
-        let radius = (texture.width() + texture.height()) / 4.0;
-        draw_circle_lines(
-            self.data.position.x + (texture.width() / 2.0),
-            self.data.position.y + (texture.height() / 2.0),
-            radius,
-            2.0,
-            RED,
-        );
-
         // draw_rectangle_lines(self.data.position.x, self.data.position.y, width, height, 2.0, RED);
     }
+
+    fn draw_vector(&self, position: Vec2) {
+        let length = self.data.sprite.height();
+        let tail = position - physics::direction(self.rotation) * length / 2.0;
+        let head = position + physics::direction(self.rotation) * length / 2.0;
+
+        vector::draw_outline(&[tail, head], false, WHITE);
+    }
 }
 
 impl Shoot {
+    /// Fires a shot centred on `position`, `velocity` being the one of the
+    /// shooter so the shot inherits it. `shots` counts the shots of the
+    /// shooter currently on screen.
     fn new(
         assets: Rc<RefCell<AssetManager>>,
-        rotation: f32,
-        position: Vec2,
         type_: ShootType,
+        position: Vec2,
+        rotation: f32,
+        velocity: Vec2,
+        weapon: &Weapon,
+        shots: Rc<RefCell<u8>>,
     ) -> Self {
-        let angle = rotation / 180.0 * PI - PI / 2.0;
-        let sound =
-            pollster::block_on(assets.borrow_mut().get_sound("res/sounds/laser1.ogg")).unwrap();
-        play_sound_once(*sound.borrow());
+        let (asset, sound) = match type_ {
+            ShootType::Player(_) => ("res/Shoot/Player.png", "res/sounds/laser1.ogg"),
+            ShootType::Enemy => ("res/Shoot/Saucer.png", "res/sounds/laser2.ogg"),
+        };
+        pollster::block_on(assets.borrow_mut().play_sound(sound, 1.0));
+
+        let sprite = pollster::block_on(assets.borrow_mut().get_sprite(asset)).unwrap();
+        *shots.borrow_mut() += 1;
 
         Self {
             type_,
-            duration: 0.5,
+            duration: weapon.lifetime,
             rotation,
+            shots,
+            piercing: weapon.piercing,
+            pierce_cooldown: 0.0,
             data: Data {
                 alive: true,
                 position,
-                impulse: Vec2 {
-                    x: f32::cos(angle),
-                    y: f32::sin(angle),
-                } * 500.0,
-                // texture: assets.borrow().get_texture("res/Shoot/Player.png"),
-                texture: pollster::block_on(
-                    assets.borrow_mut().get_texture("res/Shoot/Player.png"),
-                )
-                .unwrap(),
+                impulse: velocity + physics::direction(rotation) * weapon.speed,
+                sprite,
+                asset: asset.to_string(),
+                id: 0,
+                animation: None,
             },
         }
     }
+
+    /// Recreates a saved shot, counted in `shots` like a new one.
+    pub async fn restore(
+        assets: Rc<RefCell<AssetManager>>,
+        shots: Rc<RefCell<u8>>,
+        save: ShootSave,
+    ) -> Self {
+        *shots.borrow_mut() += 1;
+
+        Self {
+            type_: save.type_,
+            duration: save.duration,
+            rotation: save.rotation,
+            shots,
+            piercing: save.piercing,
+            pierce_cooldown: save.pierce_cooldown,
+            data: Data::restore(&assets, save.data).await,
+        }
+    }
+
+    fn kill(&mut self) {
+        if self.data.alive {
+            self.data.alive = false;
+            let mut shots = self.shots.borrow_mut();
+            *shots = shots.saturating_sub(1);
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MeteorSize {
     Big,
     Medium,
     Small,
 }
 
+impl MeteorSize {
+    /// Points scored for destroying it, the smaller the harder to hit, as
+    /// on the arcade cabinet.
+    pub fn points(&self) -> u32 {
+        match self {
+            MeteorSize::Big => 20,
+            MeteorSize::Medium => 50,
+            MeteorSize::Small => 100,
+        }
+    }
+}
+
 pub struct Meteor {
     data: Data,
     assets: Rc<RefCell<AssetManager>>,
     size: MeteorSize,
+    /// Player whose shot destroyed the meteor.
+    destroyed_by: Option<u8>,
+    /// Destroyed by a shot rather than a ship.
+    shot: bool,
 }
 
 impl Meteor {
     pub async fn new(assets: Rc<RefCell<AssetManager>>, size: MeteorSize) -> Self {
         let angle: f32 = rand::gen_range(0.0, 2.0 * PI);
 
-        let texture = match size {
+        let images = match size {
             MeteorSize::Big => vec!["Big1.png", "Big2.png", "Big3.png", "Big4.png"],
             MeteorSize::Medium => vec!["Medium1.png", "Medium2.png"],
             MeteorSize::Small => vec!["Small1.png", "Small2.png", "Small3.png", "Small4.png"],
         };
-        let texture = texture.get(rand::gen_range(0, texture.len() - 1)).unwrap();
-        let texture = assets
-            .borrow_mut()
-            .get_texture(&format!("res/Meteor/{}", texture))
-            .await
-            .unwrap();
+        let asset = format!(
+            "res/Meteor/{}",
+            images.get(rand::gen_range(0, images.len() - 1)).unwrap()
+        );
+        let sprite = assets.borrow_mut().get_sprite(&asset).await.unwrap();
 
         Self {
             size,
             assets,
+            destroyed_by: None,
+            shot: false,
             data: Data {
                 alive: true,
                 position: Vec2::default(),
@@ -414,10 +934,23 @@ impl Meteor {
                     x: angle.cos(),
                     y: angle.sin(),
                 },
-                texture,
+                sprite,
+                asset,
+                id: 0,
+                animation: None,
             },
         }
     }
+
+    pub async fn restore(assets: Rc<RefCell<AssetManager>>, save: MeteorSave) -> Self {
+        Self {
+            size: save.size,
+            data: Data::restore(&assets, save.data).await,
+            assets,
+            destroyed_by: None,
+            shot: false,
+        }
+    }
 }
 
 impl Entity for Meteor {
@@ -425,41 +958,66 @@ impl Entity for Meteor {
         self.data.position = position;
     }
 
-    fn is_collide(&self, entity: &dyn Entity) -> bool {
+    fn is_collide(&self, entity: &dyn Entity, bounds: Vec2) -> bool {
         match entity.get_type() {
-            Type::Shoot(ShootType::Player) => circle_test(&self.data, entity.get_data()),
+            // Let the shot decide, a piercing one ignores what it just went through
+            Type::Shoot(ShootType::Player(_)) => entity.is_collide(self, bounds),
             _ => false,
         }
     }
+
+    fn on_hit(&mut self, by: &Type) {
+        if let Type::Shoot(ShootType::Player(slot)) | Type::Player(slot) = by {
+            self.destroyed_by = Some(*slot);
+        }
+
+        self.shot = matches!(by, Type::Shoot(_));
+    }
     fn get_data(&self) -> &Data {
         &self.data
     }
 
+    fn get_data_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+
     fn get_type(&self) -> Type {
-        Type::Meteor(match self.size {
-            MeteorSize::Big => 10,
-            MeteorSize::Medium => 5,
-            MeteorSize::Small => 1,
-        })
+        Type::Meteor(self.size.points())
+    }
+
+    /// Fragments over the bigger meteors they fly across.
+    fn z_order(&self) -> i32 {
+        match self.size {
+            MeteorSize::Big => 0,
+            MeteorSize::Medium => 1,
+            MeteorSize::Small => 2,
+        }
     }
 
     fn is_alive(&self) -> bool {
         self.data.alive
     }
 
+    fn velocity(&self) -> Vec2 {
+        self.data.impulse * 30.0
+    }
+
+    fn save(&self) -> Option<EntitySave> {
+        Some(EntitySave::Meteor(MeteorSave {
+            size: self.size,
+            data: self.data.save(),
+        }))
+    }
+
     fn update(&mut self, dt: f32) -> Option<Box<dyn FnOnce(&mut World)>> {
-        self.data.position += dt * self.data.impulse * 30.0;
+        self.data.position += dt * self.velocity();
 
         None
     }
 
-    fn draw(&self) {
-        let texture = self.data.texture.borrow();
-
-        draw_texture_ex(
-            *texture,
-            self.data.position.x,
-            self.data.position.y,
+    fn draw(&self, position: Vec2) {
+        self.data.sprite.draw(
+            position,
             WHITE,
             DrawTextureParams {
                 // rotation: self.rotation * PI / 180.0,
@@ -469,15 +1027,16 @@ impl Entity for Meteor {
         );
 
         // draw_rectangle_lines(self.data.position.x, self.data.position.y, width, height, 1.0, RED);
-        let texture = *self.data.texture.borrow();
-        let radius = (texture.width() + texture.height()) / 4.0;
-        draw_circle_lines(
-            self.data.position.x + (texture.width() / 2.0),
-            self.data.position.y + (texture.height() / 2.0),
-            radius,
-            2.0,
-            RED,
-        );
+    }
+
+    fn draw_vector(&self, position: Vec2) {
+        let (_, radius) = collision::collider(&self.data);
+        let outline = vector::meteor(self.data.id, radius)
+            .into_iter()
+            .map(|point| position + point)
+            .collect::<Vec<_>>();
+
+        vector::draw_outline(&outline, true, WHITE);
     }
 
     fn on_destroy(&mut self) -> Option<Box<dyn FnOnce(&mut World)>> {
@@ -487,46 +1046,349 @@ impl Entity for Meteor {
             MeteorSize::Medium => "explosion2.ogg",
             _ => "explosion3.ogg",
         };
-        let sound = pollster::block_on(
+        pollster::block_on(
             self.assets
                 .borrow_mut()
-                .get_sound(&format!("res/sounds/{}", sound)),
-        )
-        .unwrap();
-        play_sound(
-            *sound.borrow(),
-            PlaySoundParams {
-                looped: false,
-                volume: 0.1,
-            },
+                .play_sound(&format!("res/sounds/{}", sound), 0.1),
         );
 
-        // play_sound_once(*sound.borrow());
-        println!("Desotry meteor");
-        let size = self.size.clone();
+        debug!(
+            "meteor destroyed size={:?} by={:?} shot={}",
+            self.size, self.destroyed_by, self.shot
+        );
+        let size = self.size;
         let assets = self.assets.clone();
-        let position = self.data.position.clone();
+        let position = self.data.position;
+        let by = self.destroyed_by;
+        let shot = self.shot;
         let nb = rand::gen_range(2, 3);
+        let explosion = self.data.sprite.width() * 1.5;
 
-        Some(Box::new(move |world| match size {
-            MeteorSize::Big => {
-                for _ in 0..nb {
-                    let mut entity =
-                        pollster::block_on(Meteor::new(assets.clone(), MeteorSize::Medium));
-                    entity.data.position = position;
-                    world.add(entity);
-                }
+        Some(Box::new(move |world| {
+            world.emit(Event::MeteorDestroyed {
+                size,
+                position,
+                by,
+                shot,
+            });
+            world.drop_power_up(assets.clone(), position);
+            if let Some(explosion) = Explosion::new(assets.clone(), position, explosion) {
+                world.add(explosion);
             }
-            MeteorSize::Medium => {
-                for _ in 0..nb {
-                    let mut entity =
-                        pollster::block_on(Meteor::new(assets.clone(), MeteorSize::Small));
-                    entity.data.position = position;
-                    world.add(entity);
+
+            match size {
+                MeteorSize::Big => {
+                    for _ in 0..nb {
+                        let mut entity =
+                            pollster::block_on(Meteor::new(assets.clone(), MeteorSize::Medium));
+                        entity.data.position = position;
+                        world.add(entity);
+                    }
                 }
+                MeteorSize::Medium => {
+                    for _ in 0..nb {
+                        let mut entity =
+                            pollster::block_on(Meteor::new(assets.clone(), MeteorSize::Small));
+                        entity.data.position = position;
+                        world.add(entity);
+                    }
+                }
+                _ => (),
             }
-            _ => (),
         }))
         // None
     }
 }
+
+pub struct PowerUp {
+    data: Data,
+    kind: PowerUpKind,
+    duration: f32,
+    collected_by: u8,
+}
+
+impl PowerUp {
+    pub fn new(
+        assets: Rc<RefCell<AssetManager>>,
+        kind: PowerUpKind,
+        position: Vec2,
+        lifetime: f32,
+    ) -> Self {
+        let angle: f32 = rand::gen_range(0.0, 2.0 * PI);
+        let asset = "res/Player/life.png";
+        let sprite = pollster::block_on(assets.borrow_mut().get_sprite(asset)).unwrap();
+
+        Self {
+            kind,
+            duration: lifetime,
+            collected_by: 0,
+            data: Data {
+                alive: true,
+                position,
+                impulse: Vec2::new(angle.cos(), angle.sin()) * 20.0,
+                sprite,
+                asset: asset.to_string(),
+                id: 0,
+                animation: None,
+            },
+        }
+    }
+
+    pub async fn restore(assets: Rc<RefCell<AssetManager>>, save: PowerUpSave) -> Self {
+        Self {
+            kind: save.kind,
+            duration: save.duration,
+            collected_by: 0,
+            data: Data::restore(&assets, save.data).await,
+        }
+    }
+}
+
+impl Entity for PowerUp {
+    fn set_position(&mut self, position: Vec2) {
+        self.data.position = position;
+    }
+
+    fn get_data(&self) -> &Data {
+        &self.data
+    }
+
+    fn get_data_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+
+    fn get_type(&self) -> Type {
+        Type::PowerUp
+    }
+
+    fn is_alive(&self) -> bool {
+        self.data.alive
+    }
+
+    fn save(&self) -> Option<EntitySave> {
+        Some(EntitySave::PowerUp(PowerUpSave {
+            kind: self.kind,
+            data: self.data.save(),
+            duration: self.duration,
+        }))
+    }
+
+    fn is_collide(&self, entity: &dyn Entity, bounds: Vec2) -> bool {
+        matches!(entity.get_type(), Type::Player(_))
+            && circle_test(&self.data, entity.get_data(), bounds)
+    }
+
+    fn on_hit(&mut self, by: &Type) {
+        if let Type::Player(slot) = by {
+            self.collected_by = *slot;
+        }
+    }
+
+    fn update(&mut self, dt: f32) -> Option<Box<dyn FnOnce(&mut World)>> {
+        self.data.position += dt * self.data.impulse;
+        self.duration -= dt;
+
+        if self.duration < 0.0 {
+            self.data.alive = false;
+        }
+
+        None
+    }
+
+    fn on_destroy(&mut self) -> Option<Box<dyn FnOnce(&mut World)>> {
+        self.data.alive = false;
+        let kind = self.kind;
+        let player = self.collected_by;
+
+        Some(Box::new(move |world| {
+            world.emit(Event::PowerUpCollected { kind, player })
+        }))
+    }
+
+    fn draw(&self, position: Vec2) {
+        // Blink when about to vanish
+        if self.duration < 2.0 && (self.duration * 8.0) as i32 % 2 == 0 {
+            return;
+        }
+
+        let color = self.kind.color();
+        self.data
+            .sprite
+            .draw(position, color, DrawTextureParams::default());
+        draw_circle_lines(
+            position.x,
+            position.y,
+            self.data.sprite.size().max_element(),
+            2.0,
+            color,
+        );
+    }
+    fn draw_vector(&self, position: Vec2) {
+        if self.duration < 2.0 && (self.duration * 8.0) as i32 % 2 == 0 {
+            return;
+        }
+
+        let radius = self.data.sprite.size().max_element();
+        let color = self.kind.color();
+        vector::draw_circle_outline(position, radius, color);
+
+        let initial = &self.kind.label()[..1];
+        draw_text(initial, position.x - 6.0, position.y + 7.0, 24.0, color);
+    }
+}
+
+/// Flipbook played where something blew up, gone once it ends.
+pub struct Explosion {
+    data: Data,
+    /// Width and height it is drawn at.
+    size: f32,
+}
+
+impl Explosion {
+    /// Explosion of `size` centred on `position`, None if the manifest has no
+    /// explosion.
+    pub fn new(assets: Rc<RefCell<AssetManager>>, position: Vec2, size: f32) -> Option<Self> {
+        let animation = pollster::block_on(assets.borrow_mut().get_animation("explosion"))?;
+
+        Some(Self {
+            size,
+            data: Data {
+                alive: true,
+                position,
+                impulse: Vec2::default(),
+                sprite: animation.sheet(),
+                asset: animation.clip().sheet.clone(),
+                id: 0,
+                animation: Some(animation),
+            },
+        })
+    }
+
+    fn progress(&self) -> f32 {
+        self.data
+            .animation
+            .as_ref()
+            .map_or(1.0, |animation| animation.progress())
+    }
+}
+
+impl Entity for Explosion {
+    fn set_position(&mut self, position: Vec2) {
+        self.data.position = position;
+    }
+
+    fn get_data(&self) -> &Data {
+        &self.data
+    }
+
+    fn get_data_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+
+    fn get_type(&self) -> Type {
+        Type::Effect
+    }
+
+    fn is_alive(&self) -> bool {
+        self.data.alive
+    }
+
+    fn update(&mut self, _dt: f32) -> Option<Box<dyn FnOnce(&mut World)>> {
+        if self
+            .data
+            .animation
+            .as_ref()
+            .is_none_or(|animation| animation.is_finished())
+        {
+            self.data.alive = false;
+        }
+
+        None
+    }
+
+    fn draw(&self, position: Vec2) {
+        if let Some(animation) = self.data.animation.as_ref() {
+            animation.draw(
+                position,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(Vec2::splat(self.size)),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn draw_vector(&self, position: Vec2) {
+        let progress = self.progress();
+        let color = Color::new(ORANGE.r, ORANGE.g, ORANGE.b, 1.0 - progress);
+
+        vector::draw_circle_outline(position, self.size * 0.5 * (0.2 + 0.8 * progress), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::{Rect, Texture2D};
+
+    use super::*;
+
+    /// Meteor built without loading anything, the window being needed for
+    /// that.
+    fn meteor(size: MeteorSize, position: Vec2, impulse: Vec2) -> Meteor {
+        Meteor {
+            data: Data {
+                alive: true,
+                position,
+                impulse,
+                sprite: Sprite {
+                    texture: Texture2D::empty(),
+                    source: Rect::new(0.0, 0.0, 40.0, 40.0),
+                },
+                asset: "res/Meteor/Big1.png".to_string(),
+                id: 0,
+                animation: None,
+            },
+            assets: Rc::new(RefCell::new(AssetManager::new())),
+            size,
+            destroyed_by: None,
+            shot: false,
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_json() {
+        let mut world = World::new();
+        world.add(meteor(
+            MeteorSize::Big,
+            Vec2::new(100.5, 200.25),
+            Vec2::new(1.0, -0.5),
+        ));
+        world.add(meteor(
+            MeteorSize::Small,
+            Vec2::new(-3.0, 4.0e3),
+            Vec2::ZERO,
+        ));
+        world.update(0.0);
+
+        let json = world.to_json().unwrap();
+        let snapshot = World::snapshot_from_json(&json).unwrap();
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot, world.snapshot());
+    }
+
+    #[test]
+    fn checksum_covers_more_than_positions() {
+        let world = |impulse| {
+            let mut world = World::new();
+            world.add(meteor(MeteorSize::Big, Vec2::new(100.0, 200.0), impulse));
+            world.update(0.0);
+            world
+        };
+
+        let still = world(Vec2::ZERO);
+        assert_eq!(still.checksum(), world(Vec2::ZERO).checksum());
+        assert_ne!(still.checksum(), world(Vec2::new(0.0, 1.0)).checksum());
+    }
+}
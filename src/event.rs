@@ -0,0 +1,46 @@
+use macroquad::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entity::{MeteorSize, Type},
+    power_up::PowerUpKind,
+};
+
+/// Why a ship lost a life.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DeathCause {
+    /// Hit by an entity of the given type.
+    Collision(Type),
+    /// Exploded when coming back from a failed hyperspace jump.
+    Hyperspace,
+}
+
+/// Gameplay events raised by the entities, drained by the game state once the
+/// world has been updated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// A meteor has been destroyed, `by` being the player who shot or
+    /// rammed it.
+    MeteorDestroyed {
+        size: MeteorSize,
+        position: Vec2,
+        by: Option<u8>,
+        /// Destroyed by a shot rather than a ship.
+        shot: bool,
+    },
+    PowerUpCollected {
+        kind: PowerUpKind,
+        player: u8,
+    },
+    ShotFired {
+        player: u8,
+    },
+    HyperspaceJump {
+        player: u8,
+    },
+    /// A ship lost a life.
+    ShipDestroyed {
+        player: u8,
+        cause: DeathCause,
+    },
+}
@@ -0,0 +1,29 @@
+/// Arcade rule awarding an extra ship every `every` points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExtraLifeConfig {
+    /// Points needed for each extra ship, 0 disables the rule.
+    pub every: u32,
+    /// Lives can't go past this number.
+    pub max_lives: u8,
+}
+
+impl Default for ExtraLifeConfig {
+    fn default() -> Self {
+        Self {
+            every: 10_000,
+            max_lives: 9,
+        }
+    }
+}
+
+impl ExtraLifeConfig {
+    /// Number of thresholds crossed when the score goes from `before` to
+    /// `after`.
+    pub fn earned(&self, before: u32, after: u32) -> u32 {
+        if self.every == 0 || after <= before {
+            return 0;
+        }
+
+        after / self.every - before / self.every
+    }
+}
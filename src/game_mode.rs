@@ -0,0 +1,155 @@
+use std::{collections::HashMap, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{daily::Date, hyperspace::HyperspaceConfig, persist};
+
+const PATH: &str = "high_scores.json";
+
+/// Scores kept in each high-score table.
+const HIGH_SCORES: usize = 5;
+
+/// Rule set of a game.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Waves of meteors until every life is lost.
+    #[default]
+    Classic,
+    /// Highest score before the time runs out.
+    TimeAttack,
+    /// Meteors keep coming faster and faster, with a single life.
+    Survival,
+    /// Lives are never lost, for practice.
+    Zen,
+    /// Classic rules on the meteor layout of the day, the same for everyone.
+    Daily,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::Survival,
+        GameMode::Zen,
+        GameMode::Daily,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Survival => "Survival",
+            GameMode::Zen => "Zen",
+            GameMode::Daily => "Daily",
+        }
+    }
+
+    /// Mode after this one, to cycle through them from the menu.
+    pub fn next(&self) -> GameMode {
+        let index = GameMode::ALL.iter().position(|mode| mode == self).unwrap();
+
+        GameMode::ALL[(index + 1) % GameMode::ALL.len()]
+    }
+
+    pub fn lives(&self) -> u8 {
+        match self {
+            GameMode::Survival => 1,
+            _ => 3,
+        }
+    }
+
+    pub fn loses_lives(&self) -> bool {
+        *self != GameMode::Zen
+    }
+
+    /// Seconds the game lasts, if it is timed.
+    pub fn time_limit(&self) -> Option<f32> {
+        match self {
+            GameMode::TimeAttack => Some(180.0),
+            _ => None,
+        }
+    }
+
+    /// Seconds between two meteors spawned mid-wave after `elapsed` seconds of
+    /// play, if they spawn at all.
+    pub fn spawn_interval(&self, elapsed: f32) -> Option<f32> {
+        match self {
+            GameMode::Survival => Some((8.0 - elapsed / 30.0).max(1.5)),
+            _ => None,
+        }
+    }
+
+    /// What the escape key does: a shield when a single life can't be gambled
+    /// on a jump, and never landing in a meteor while practising.
+    pub fn hyperspace(&self) -> HyperspaceConfig {
+        match self {
+            GameMode::Survival => HyperspaceConfig::shield(),
+            GameMode::Zen => HyperspaceConfig::safe(),
+            _ => HyperspaceConfig::default(),
+        }
+    }
+}
+
+/// Best scores of each game mode, and of each daily challenge.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    tables: HashMap<GameMode, Vec<u32>>,
+    /// Tables of the daily challenges, by date.
+    daily: HashMap<String, Vec<u32>>,
+}
+
+impl HighScores {
+    /// Reads the high scores, empty if they were never saved.
+    pub fn load() -> Self {
+        persist::load_json(PATH)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        persist::save_json(PATH, self)
+    }
+
+    /// Best scores of `mode`, highest first.
+    pub fn table(&self, mode: GameMode) -> &[u32] {
+        self.tables
+            .get(&mode)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Best scores of the daily challenge of `date`, highest first.
+    pub fn daily_table(&self, date: Date) -> &[u32] {
+        self.daily
+            .get(&date.to_string())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Enters `score` in the table of `mode`, returns whether it made it.
+    pub fn submit(&mut self, mode: GameMode, score: u32) -> bool {
+        enter(self.tables.entry(mode).or_default(), score)
+    }
+
+    /// Enters `score` in the table of the daily challenge of `date`, returns
+    /// whether it made it.
+    pub fn submit_daily(&mut self, date: Date, score: u32) -> bool {
+        enter(self.daily.entry(date.to_string()).or_default(), score)
+    }
+}
+
+/// Enters `score` in `table` at its rank, returns whether it made it.
+fn enter(table: &mut Vec<u32>, score: u32) -> bool {
+    if score == 0 {
+        return false;
+    }
+
+    let rank = table.partition_point(|best| *best >= score);
+    if rank >= HIGH_SCORES {
+        return false;
+    }
+
+    table.insert(rank, score);
+    table.truncate(HIGH_SCORES);
+
+    true
+}
@@ -0,0 +1,329 @@
+use std::{cell::RefCell, rc::Rc};
+
+use macroquad::prelude::*;
+
+use crate::{arena::Arena, assets_manager::AssetManager, atlas::Sprite, power_up::PowerUpKind};
+
+/// Seconds a centre message stays on screen, fading out at the end.
+const MESSAGE_TIME: f32 = 2.0;
+
+/// Seconds a score pop-up rises before vanishing.
+const POPUP_TIME: f32 = 0.8;
+
+/// Virtual pixels a score pop-up rises by.
+const POPUP_RISE: f32 = 30.0;
+
+/// Space between the edges of the arena and the HUD, in virtual pixels.
+const MARGIN: f32 = 8.0;
+
+/// Formats `score` with thousands separators.
+pub fn format_score(score: u32) -> String {
+    let digits = score.to_string();
+    let mut formatted = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
+/// What the HUD shows of a player.
+pub struct PlayerPanel {
+    /// Shown before the score when several people play.
+    pub label: Option<String>,
+    pub score: u32,
+    pub lives: u8,
+    /// Hides the newest ship of the lives row, to blink it.
+    pub hide_newest: bool,
+    pub color: Color,
+    pub power_ups: Vec<(PowerUpKind, f32)>,
+}
+
+/// What the HUD shows this frame.
+pub struct Panel {
+    pub players: Vec<PlayerPanel>,
+    pub high_score: u32,
+    pub wave: u32,
+    /// Seconds left in a timed game.
+    pub time_left: Option<u32>,
+    /// Shown at the bottom, like the state of an online game.
+    pub status: Option<String>,
+}
+
+/// Where a text is laid out from, in the part of the window showing the
+/// arena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Anchor {
+    TopLeft,
+    Top,
+    Center,
+    Bottom,
+}
+
+/// Lays the HUD out in the viewport, sizes being given in virtual pixels and
+/// scaled with it.
+struct Layout {
+    viewport: Rect,
+    /// Screen pixels per virtual pixel on each axis.
+    arena_scale: Vec2,
+    /// Screen pixels per virtual pixel of the HUD sizes.
+    scale: f32,
+    font: Font,
+}
+
+impl Layout {
+    fn new(arena: &Arena, font: Font) -> Self {
+        let arena_scale = arena.scale();
+
+        Self {
+            viewport: arena.viewport(),
+            arena_scale,
+            scale: arena_scale.y,
+            font,
+        }
+    }
+
+    fn font_size(&self, size: f32) -> u16 {
+        (size * self.scale).round().max(8.0) as u16
+    }
+
+    /// Draws `text` of `size` at `offset` from `anchor`, the offset going
+    /// inwards from the edges.
+    fn text(&self, text: &str, anchor: Anchor, offset: f32, size: f32, color: Color) {
+        let font_size = self.font_size(size);
+        let dimensions = measure_text(text, Some(self.font), font_size, 1.0);
+        let margin = MARGIN * self.scale;
+        let offset = offset * self.scale;
+        let viewport = self.viewport;
+
+        let x = match anchor {
+            Anchor::TopLeft => viewport.x + margin,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => {
+                viewport.center().x - dimensions.width * 0.5
+            }
+        };
+        let top = match anchor {
+            Anchor::TopLeft | Anchor::Top => viewport.y + margin + offset,
+            Anchor::Center => viewport.center().y - dimensions.height * 0.5 + offset,
+            Anchor::Bottom => viewport.bottom() - margin - offset - dimensions.height,
+        };
+
+        draw_text_ex(
+            text,
+            x,
+            top + dimensions.offset_y,
+            TextParams {
+                font: self.font,
+                font_size,
+                color,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Screen position of `position` in the arena.
+    fn to_screen(&self, position: Vec2) -> Vec2 {
+        self.viewport.point() + position * self.arena_scale
+    }
+}
+
+struct Popup {
+    text: String,
+    /// Where it was scored, in the arena.
+    position: Vec2,
+    time: f32,
+}
+
+/// Scores, lives and messages drawn over the arena.
+pub struct Hud {
+    font: Font,
+    life: Sprite,
+    /// Centre messages, the first one being shown.
+    messages: Vec<String>,
+    /// Time the first message has been shown.
+    time: f32,
+    popups: Vec<Popup>,
+}
+
+impl Hud {
+    pub async fn new(assets: &Rc<RefCell<AssetManager>>) -> Self {
+        let font = assets
+            .borrow_mut()
+            .get_font("res/trs-million.ttf")
+            .await
+            .unwrap();
+        let life = assets
+            .borrow_mut()
+            .get_sprite("res/Player/life.png")
+            .await
+            .unwrap();
+        let font = *font.borrow();
+
+        Self {
+            font,
+            life,
+            messages: vec![],
+            time: 0.0,
+            popups: vec![],
+        }
+    }
+
+    pub fn font(&self) -> Font {
+        self.font
+    }
+
+    /// Shows `text` in the centre once the messages before it are gone.
+    pub fn message(&mut self, text: impl Into<String>) {
+        self.messages.push(text.into());
+    }
+
+    pub fn clear_messages(&mut self) {
+        self.messages.clear();
+        self.time = 0.0;
+    }
+
+    /// Shows `points` rising from `position` in the arena.
+    pub fn popup(&mut self, points: u32, position: Vec2) {
+        self.popups.push(Popup {
+            text: format_score(points),
+            position,
+            time: 0.0,
+        });
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if !self.messages.is_empty() {
+            self.time += dt;
+            if self.time >= MESSAGE_TIME {
+                self.messages.remove(0);
+                self.time = 0.0;
+            }
+        }
+
+        for popup in self.popups.iter_mut() {
+            popup.time += dt;
+        }
+        self.popups.retain(|popup| popup.time < POPUP_TIME);
+    }
+
+    /// Draws the score pop-ups, the messages and `panel` over `arena`, in
+    /// screen pixels.
+    pub fn draw(&self, arena: &Arena, panel: &Panel) {
+        let layout = Layout::new(arena, self.font);
+
+        for popup in self.popups.iter() {
+            let progress = popup.time / POPUP_TIME;
+            let position = layout.to_screen(popup.position - vec2(0.0, POPUP_RISE * progress));
+            let font_size = layout.font_size(18.0);
+            let dimensions = measure_text(&popup.text, Some(self.font), font_size, 1.0);
+            draw_text_ex(
+                &popup.text,
+                position.x - dimensions.width * 0.5,
+                position.y,
+                TextParams {
+                    font: self.font,
+                    font_size,
+                    color: Color::new(1.0, 1.0, 1.0, 1.0 - progress),
+                    ..Default::default()
+                },
+            );
+        }
+
+        if let Some(message) = self.messages.first() {
+            let fade = ((MESSAGE_TIME - self.time) / 0.5).min(1.0);
+            layout.text(
+                message,
+                Anchor::Center,
+                0.0,
+                50.0,
+                Color::new(1.0, 1.0, 1.0, fade),
+            );
+        }
+
+        self.draw_panel(&layout, panel);
+    }
+
+    fn draw_panel(&self, layout: &Layout, panel: &Panel) {
+        let mut offset = 0.0;
+        for player in panel.players.iter() {
+            let score = format_score(player.score);
+            let text = match player.label.as_ref() {
+                Some(label) => format!("{} {}", label, score),
+                None => score,
+            };
+            layout.text(&text, Anchor::TopLeft, offset, 30.0, player.color);
+            offset += 32.0;
+        }
+
+        for player in panel.players.iter() {
+            for (kind, remaining) in player.power_ups.iter() {
+                let text = match player.label.as_ref() {
+                    Some(label) => format!("{} {} {:.0}", label, kind.label(), remaining.ceil()),
+                    None => format!("{} {:.0}", kind.label(), remaining.ceil()),
+                };
+                layout.text(&text, Anchor::TopLeft, offset, 20.0, kind.color());
+                offset += 22.0;
+            }
+        }
+
+        let high_score = format!("HI {}", format_score(panel.high_score));
+        layout.text(&high_score, Anchor::Top, 0.0, 20.0, LIGHTGRAY);
+        layout.text(
+            &format!("WAVE {}", panel.wave),
+            Anchor::Top,
+            22.0,
+            16.0,
+            GRAY,
+        );
+
+        if let Some(left) = panel.time_left {
+            let text = format!("{}:{:02}", left / 60, left % 60);
+            let color = if left <= 10 { RED } else { WHITE };
+            layout.text(&text, Anchor::Top, 42.0, 30.0, color);
+        }
+
+        if let Some(status) = panel.status.as_ref() {
+            layout.text(status, Anchor::Bottom, 0.0, 30.0, YELLOW);
+        }
+
+        // One row of ships per player, the newest on the left
+        let size = self.life.size() * layout.scale;
+        let margin = MARGIN * layout.scale;
+        for (row, player) in panel.players.iter().enumerate() {
+            for i in 0..player.lives {
+                if i == 0 && player.hide_newest {
+                    continue;
+                }
+
+                let corner = vec2(
+                    layout.viewport.right() - margin - (player.lives - i) as f32 * size.x,
+                    layout.viewport.y + margin + row as f32 * size.y,
+                );
+                self.life.draw(
+                    corner + size / 2.0,
+                    player.color,
+                    DrawTextureParams {
+                        dest_size: Some(size),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_has_thousands_separators() {
+        assert_eq!(format_score(0), "0");
+        assert_eq!(format_score(999), "999");
+        assert_eq!(format_score(1000), "1,000");
+        assert_eq!(format_score(1234567), "1,234,567");
+    }
+}
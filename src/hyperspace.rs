@@ -0,0 +1,79 @@
+/// What the escape key does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscapeMode {
+    /// Vanish and reappear somewhere else on the screen.
+    Hyperspace,
+    /// Raise a shield for a short while.
+    Shield,
+}
+
+/// Tuning of the escape key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HyperspaceConfig {
+    pub mode: EscapeMode,
+    /// Delay in seconds between two jumps.
+    pub cooldown: f32,
+    /// Chance between 0.0 and 1.0 to explode on the first jump.
+    pub failure_chance: f32,
+    /// Added to the chance to explode after each jump.
+    pub failure_per_jump: f32,
+    pub max_failure_chance: f32,
+    /// Avoid reappearing inside a meteor.
+    pub safe_landing: bool,
+    /// Distance in pixels to keep from meteors when landing safely.
+    pub safe_radius: f32,
+    /// Time in seconds the ship stays invisible before reappearing.
+    pub vanish_time: f32,
+    /// Time in seconds the shield lasts in [`EscapeMode::Shield`].
+    pub shield_duration: f32,
+    /// Delay in seconds between two shields in [`EscapeMode::Shield`].
+    pub shield_cooldown: f32,
+}
+
+impl Default for HyperspaceConfig {
+    fn default() -> Self {
+        Self {
+            mode: EscapeMode::Hyperspace,
+            cooldown: 1.0,
+            failure_chance: 0.0,
+            failure_per_jump: 0.05,
+            max_failure_chance: 0.5,
+            safe_landing: false,
+            safe_radius: 80.0,
+            vanish_time: 0.5,
+            shield_duration: 2.0,
+            shield_cooldown: 5.0,
+        }
+    }
+}
+
+impl HyperspaceConfig {
+    /// Escape key raising a shield rather than jumping, for rules where the
+    /// gamble of a jump is unwelcome.
+    pub fn shield() -> Self {
+        Self {
+            mode: EscapeMode::Shield,
+            ..Default::default()
+        }
+    }
+
+    /// Jumps which never land inside a meteor, for practice.
+    pub fn safe() -> Self {
+        Self {
+            safe_landing: true,
+            ..Default::default()
+        }
+    }
+
+    /// Chance to explode when jumping after `jumps` previous jumps.
+    pub fn failure_chance(&self, jumps: u32) -> f32 {
+        (self.failure_chance + jumps as f32 * self.failure_per_jump).min(self.max_failure_chance)
+    }
+
+    pub fn cooldown(&self) -> f32 {
+        match self.mode {
+            EscapeMode::Hyperspace => self.cooldown,
+            EscapeMode::Shield => self.shield_cooldown,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use macroquad::prelude::{is_key_down, is_key_pressed, is_key_released, KeyCode};
+
+/// Controls of a ship for the current frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShipInput {
+    pub thrust: bool,
+    /// Steering between -1.0 (left) and 1.0 (right).
+    pub turn: f32,
+    /// The fire key is held down.
+    pub fire: bool,
+    /// The fire key has been pressed this frame.
+    pub fire_pressed: bool,
+    /// The escape key has been released this frame.
+    pub escape: bool,
+}
+
+impl ShipInput {
+    const THRUST: u8 = 1;
+    const FIRE: u8 = 1 << 1;
+    const FIRE_PRESSED: u8 = 1 << 2;
+    const ESCAPE: u8 = 1 << 3;
+    const LEFT: u8 = 1 << 4;
+    const RIGHT: u8 = 1 << 5;
+
+    /// Packs the input in a single byte to send it over the network.
+    pub fn to_bits(&self) -> u8 {
+        let mut bits = 0;
+
+        for (set, bit) in [
+            (self.thrust, Self::THRUST),
+            (self.fire, Self::FIRE),
+            (self.fire_pressed, Self::FIRE_PRESSED),
+            (self.escape, Self::ESCAPE),
+            (self.turn < 0.0, Self::LEFT),
+            (self.turn > 0.0, Self::RIGHT),
+        ] {
+            if set {
+                bits |= bit;
+            }
+        }
+
+        bits
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        let turn = if bits & Self::RIGHT != 0 {
+            1.0
+        } else if bits & Self::LEFT != 0 {
+            -1.0
+        } else {
+            0.0
+        };
+
+        Self {
+            thrust: bits & Self::THRUST != 0,
+            turn,
+            fire: bits & Self::FIRE != 0,
+            fire_pressed: bits & Self::FIRE_PRESSED != 0,
+            escape: bits & Self::ESCAPE != 0,
+        }
+    }
+
+    /// Merges the input sampled on a frame the simulation did not advance,
+    /// so key presses and releases are not lost.
+    pub fn merge(&mut self, next: ShipInput) {
+        let fire_pressed = self.fire_pressed || next.fire_pressed;
+        let escape = self.escape || next.escape;
+
+        *self = ShipInput {
+            fire_pressed,
+            escape,
+            ..next
+        };
+    }
+}
+
+/// Keys controlling a ship.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bindings {
+    pub thrust: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub fire: KeyCode,
+    pub escape: KeyCode,
+}
+
+impl Bindings {
+    pub const ARROWS: Bindings = Bindings {
+        thrust: KeyCode::Up,
+        left: KeyCode::Left,
+        right: KeyCode::Right,
+        fire: KeyCode::Space,
+        escape: KeyCode::Down,
+    };
+
+    pub const WASD: Bindings = Bindings {
+        thrust: KeyCode::W,
+        left: KeyCode::A,
+        right: KeyCode::D,
+        fire: KeyCode::LeftShift,
+        escape: KeyCode::S,
+    };
+
+    /// Reads the state of the keys from the keyboard.
+    pub fn sample(&self) -> ShipInput {
+        let turn = if is_key_down(self.right) {
+            1.0
+        } else if is_key_down(self.left) {
+            -1.0
+        } else {
+            0.0
+        };
+
+        ShipInput {
+            thrust: is_key_down(self.thrust),
+            turn,
+            fire: is_key_down(self.fire),
+            fire_pressed: is_key_pressed(self.fire),
+            escape: is_key_released(self.escape),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::entity::Type;
+
+/// Render layers from the bottom to the top. The game draws the background
+/// and the HUD on theirs, and the world its entities on the others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Background,
+    Meteors,
+    Enemies,
+    PowerUps,
+    Bullets,
+    Player,
+    Particles,
+    Hud,
+}
+
+impl Layer {
+    /// Every layer, in the order they are drawn.
+    pub const ALL: [Layer; 8] = [
+        Layer::Background,
+        Layer::Meteors,
+        Layer::Enemies,
+        Layer::PowerUps,
+        Layer::Bullets,
+        Layer::Player,
+        Layer::Particles,
+        Layer::Hud,
+    ];
+
+    /// Layer entities of `type_` are drawn on unless they pick another.
+    pub fn of(type_: &Type) -> Layer {
+        match type_ {
+            Type::Meteor(_) => Layer::Meteors,
+            Type::Enemy => Layer::Enemies,
+            Type::PowerUp => Layer::PowerUps,
+            Type::Shoot(_) => Layer::Bullets,
+            Type::Player(_) => Layer::Player,
+            Type::Effect => Layer::Particles,
+        }
+    }
+}
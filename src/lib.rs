@@ -1,5 +1,32 @@
+pub mod achievements;
+pub mod animation;
+pub mod arena;
 pub mod assets_manager;
+pub mod atlas;
 pub mod collision;
+pub mod daily;
+pub mod debug;
 pub mod entity;
+pub mod event;
+pub mod extra_life;
+pub mod game_mode;
+pub mod hud;
+pub mod hyperspace;
+pub mod input;
+pub mod layer;
+pub mod logging;
+pub mod net;
+pub mod persist;
+pub mod physics;
+pub mod post;
+pub mod power_up;
+pub mod save;
+pub mod settings;
+pub mod spawn;
+pub mod starfield;
 pub mod state_manager;
+pub mod stats;
+pub mod telemetry;
+pub mod vector;
+pub mod weapon;
 pub mod world;
@@ -0,0 +1,38 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Writes the records to stderr as `LEVEL target: message`.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{:<5} {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger;
+
+/// Installs the logger, the level being read from `ASTEROIDS_LOG` (`off`,
+/// `error`, `warn`, `info`, `debug` or `trace`) and defaulting to `info`.
+pub fn init() {
+    let level = std::env::var("ASTEROIDS_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(LevelFilter::Info);
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -1,15 +1,43 @@
-use asteroids::{assets_manager::AssetManager, state_manager::StateManager};
+use asteroids::{
+    arena, assets_manager::AssetManager, logging, net::Lockstep, state_manager::StateManager,
+};
+use log::error;
 use macroquad::{
     audio::{play_sound, PlaySoundParams},
+    input::{is_quit_requested, prevent_quit},
     window::next_frame,
 };
 use std::{cell::RefCell, rc::Rc};
 
 #[macroquad::main("Asteroids")]
 async fn main() {
+    logging::init();
+
     let assets_manager = Rc::new(RefCell::new(AssetManager::new()));
     let mut state_manager = StateManager::new(assets_manager.clone()).await;
 
+    // `--host <port> [--versus]` or `--join <address>` for an online game
+    let args: Vec<String> = std::env::args().collect();
+    let versus = args.iter().any(|arg| arg == "--versus");
+    let session = match args.get(1).map(String::as_str) {
+        Some("--host") => Some(Lockstep::host(
+            args.get(2)
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(7777),
+            arena::SIZE,
+            versus,
+        )),
+        Some("--join") => Some(Lockstep::join(
+            args.get(2).map_or("127.0.0.1:7777", String::as_str),
+        )),
+        _ => None,
+    };
+    match session {
+        Some(Ok(session)) => state_manager.start_online(session),
+        Some(Err(error)) => error!("online game not started error={}", error),
+        None => (),
+    }
+
     // #[cfg(not(debug_assertions))]
     // {
     let sound = (*assets_manager.borrow_mut())
@@ -26,11 +54,15 @@ async fn main() {
 
     let mut running = true;
 
+    // Closing the window saves the game in progress first
+    prevent_quit();
+
     loop {
-        running = state_manager.update();
+        running = state_manager.update() && !is_quit_requested();
         state_manager.draw();
 
         if !running {
+            state_manager.quit();
             break;
         }
 
@@ -0,0 +1,367 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use macroquad::prelude::{rand, Vec2};
+
+use crate::input::ShipInput;
+
+/// Duration of a lockstep frame, every peer must simulate with the same one.
+pub const FIXED_DT: f32 = 1.0 / 60.0;
+
+/// Frames between sampling a local input and applying it, giving it time to
+/// reach the other peer.
+const INPUT_DELAY: u32 = 3;
+
+/// Number of past inputs repeated in each packet to make up for lost ones.
+const INPUT_WINDOW: u32 = 8;
+
+/// Polls between two hello packets while joining.
+const HELLO_INTERVAL: u32 = 30;
+
+const HELLO: u8 = 0;
+const WELCOME: u8 = 1;
+const INPUTS: u8 = 2;
+
+/// Reseeds the random generator before simulating `frame`, so every peer
+/// draws the same numbers whatever ran before.
+pub fn reseed(seed: u64, frame: u32) {
+    rand::srand(seed.wrapping_add(frame as u64));
+}
+
+enum Packet {
+    /// Sent by the joining peer until the host answers.
+    Hello,
+    /// Sent by the host with what both peers need to simulate the same game.
+    Welcome {
+        seed: u64,
+        arena: Vec2,
+        friendly_fire: bool,
+    },
+    /// Inputs of the sender from `first` on, with the checksum of its world
+    /// at a given frame.
+    Inputs {
+        first: u32,
+        inputs: Vec<ShipInput>,
+        checksum: Option<(u32, u64)>,
+    },
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+
+        match self {
+            Packet::Hello => bytes.push(HELLO),
+            Packet::Welcome {
+                seed,
+                arena,
+                friendly_fire,
+            } => {
+                bytes.push(WELCOME);
+                bytes.extend_from_slice(&seed.to_le_bytes());
+                bytes.extend_from_slice(&arena.x.to_le_bytes());
+                bytes.extend_from_slice(&arena.y.to_le_bytes());
+                bytes.push(u8::from(*friendly_fire));
+            }
+            Packet::Inputs {
+                first,
+                inputs,
+                checksum,
+            } => {
+                bytes.push(INPUTS);
+                bytes.extend_from_slice(&first.to_le_bytes());
+                bytes.push(inputs.len() as u8);
+                bytes.extend(inputs.iter().map(ShipInput::to_bits));
+
+                if let Some((frame, checksum)) = checksum {
+                    bytes.extend_from_slice(&frame.to_le_bytes());
+                    bytes.extend_from_slice(&checksum.to_le_bytes());
+                }
+            }
+        }
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let u32_at = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+        let u64_at = |at: usize| Some(u64::from_le_bytes(bytes.get(at..at + 8)?.try_into().ok()?));
+        let f32_at = |at: usize| Some(f32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+
+        match *bytes.first()? {
+            HELLO => Some(Packet::Hello),
+            WELCOME => Some(Packet::Welcome {
+                seed: u64_at(1)?,
+                arena: Vec2::new(f32_at(9)?, f32_at(13)?),
+                friendly_fire: *bytes.get(17)? != 0,
+            }),
+            INPUTS => {
+                let count = *bytes.get(5)? as usize;
+                let inputs = bytes
+                    .get(6..6 + count)?
+                    .iter()
+                    .map(|bits| ShipInput::from_bits(*bits))
+                    .collect();
+                let checksum = u32_at(6 + count).zip(u64_at(10 + count));
+
+                Some(Packet::Inputs {
+                    first: u32_at(1)?,
+                    inputs,
+                    checksum,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Deterministic lockstep session between two peers over UDP.
+///
+/// Each peer sends its inputs ahead of time and only advances its world once
+/// it knows the inputs of both ships for the next frame. Worlds are compared
+/// through checksums to detect a desync.
+pub struct Lockstep {
+    socket: UdpSocket,
+    remote: Option<SocketAddr>,
+    /// Seed shared by both peers, known once the session is connected.
+    seed: Option<u64>,
+    /// Size of the arena of the host, both peers must simulate the same one.
+    pub arena: Vec2,
+    /// Ships can shoot each other, as decided by the host.
+    pub friendly_fire: bool,
+    local_slot: u8,
+    frame: u32,
+    polls: u32,
+    local_inputs: BTreeMap<u32, ShipInput>,
+    remote_inputs: BTreeMap<u32, ShipInput>,
+    local_checksums: BTreeMap<u32, u64>,
+    remote_checksums: BTreeMap<u32, u64>,
+    desync: Option<u32>,
+}
+
+impl Lockstep {
+    /// Waits for a peer on `port`, the host controls the first ship.
+    pub fn host(port: u16, arena: Vec2, friendly_fire: bool) -> io::Result<Self> {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+
+        let mut session = Self::new(UdpSocket::bind(("0.0.0.0", port))?, None, Some(seed), 0)?;
+        session.arena = arena;
+        session.friendly_fire = friendly_fire;
+
+        Ok(session)
+    }
+
+    /// Connects to the host at `address`, the joining peer controls the
+    /// second ship.
+    pub fn join(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let remote = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to join"))?;
+
+        Self::new(UdpSocket::bind(("0.0.0.0", 0))?, Some(remote), None, 1)
+    }
+
+    fn new(
+        socket: UdpSocket,
+        remote: Option<SocketAddr>,
+        seed: Option<u64>,
+        local_slot: u8,
+    ) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+
+        let mut session = Self {
+            socket,
+            remote,
+            seed,
+            arena: Vec2::ZERO,
+            friendly_fire: false,
+            local_slot,
+            frame: 0,
+            polls: 0,
+            local_inputs: BTreeMap::new(),
+            remote_inputs: BTreeMap::new(),
+            local_checksums: BTreeMap::new(),
+            remote_checksums: BTreeMap::new(),
+            desync: None,
+        };
+
+        // Nobody moves until the first inputs had time to go through
+        for frame in 0..INPUT_DELAY {
+            session.local_inputs.insert(frame, ShipInput::default());
+            session.remote_inputs.insert(frame, ShipInput::default());
+        }
+
+        Ok(session)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.remote.is_some() && self.seed.is_some()
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn local_slot(&self) -> u8 {
+        self.local_slot
+    }
+
+    /// Next frame to simulate.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// First frame where both worlds diverged, if any.
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    fn send(&self, packet: &Packet) {
+        if let Some(remote) = self.remote {
+            // A lost packet is made up for by the next ones
+            let _ = self.socket.send_to(&packet.encode(), remote);
+        }
+    }
+
+    /// Handles the packets received since the last call.
+    pub fn poll(&mut self) {
+        if self.seed.is_none() && self.polls % HELLO_INTERVAL == 0 {
+            self.send(&Packet::Hello);
+        }
+        self.polls += 1;
+
+        let mut buffer = [0; 512];
+        while let Ok((size, from)) = self.socket.recv_from(&mut buffer) {
+            // Once bound to a peer, anyone else is ignored
+            if self.remote.is_some_and(|remote| remote != from) {
+                continue;
+            }
+
+            match Packet::decode(&buffer[..size]) {
+                Some(Packet::Hello) => {
+                    // Only the host knows the seed, and answers again in case
+                    // its welcome got lost
+                    if let Some(seed) = self.seed {
+                        self.remote = Some(from);
+                        self.send(&Packet::Welcome {
+                            seed,
+                            arena: self.arena,
+                            friendly_fire: self.friendly_fire,
+                        });
+                    }
+                }
+                Some(Packet::Welcome {
+                    seed,
+                    arena,
+                    friendly_fire,
+                }) if self.seed.is_none() => {
+                    self.seed = Some(seed);
+                    self.arena = arena;
+                    self.friendly_fire = friendly_fire;
+                }
+                Some(Packet::Inputs {
+                    first,
+                    inputs,
+                    checksum,
+                }) => {
+                    for (frame, input) in (first..).zip(inputs) {
+                        self.remote_inputs.entry(frame).or_insert(input);
+                    }
+
+                    if let Some((frame, checksum)) = checksum {
+                        self.remote_checksums.insert(frame, checksum);
+                        self.check(frame);
+                    }
+                }
+                Some(Packet::Welcome { .. }) | None => (),
+            }
+        }
+    }
+
+    /// Schedules the local `input` and sends it to the other peer, along with
+    /// the previous ones in case some got lost.
+    ///
+    /// Returns false when the input of the upcoming frame is already known,
+    /// the simulation waiting for the other peer.
+    pub fn push_local(&mut self, input: ShipInput) -> bool {
+        let frame = self.frame + INPUT_DELAY;
+        let scheduled = !self.local_inputs.contains_key(&frame);
+        self.local_inputs.entry(frame).or_insert(input);
+
+        let first = frame.saturating_sub(INPUT_WINDOW - 1);
+        let inputs = self
+            .local_inputs
+            .range(first..=frame)
+            .map(|(_, input)| *input)
+            .collect();
+        let checksum = self
+            .local_checksums
+            .iter()
+            .next_back()
+            .map(|(frame, checksum)| (*frame, *checksum));
+
+        self.send(&Packet::Inputs {
+            first,
+            inputs,
+            checksum,
+        });
+
+        scheduled
+    }
+
+    /// Inputs of both ships for the next frame, ordered by slot, once known.
+    pub fn inputs(&self) -> Option<[ShipInput; 2]> {
+        let local = *self.local_inputs.get(&self.frame)?;
+        let remote = *self.remote_inputs.get(&self.frame)?;
+
+        Some(if self.local_slot == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        })
+    }
+
+    /// Records the `checksum` of the world once the next frame has been
+    /// simulated, and moves on to the following one.
+    pub fn advance(&mut self, checksum: u64) {
+        self.local_checksums.insert(self.frame, checksum);
+        self.check(self.frame);
+        self.frame += 1;
+
+        // Forget what can't be needed anymore
+        let oldest = self.frame.saturating_sub(INPUT_WINDOW * 8);
+        for history in [&mut self.local_inputs, &mut self.remote_inputs] {
+            *history = history.split_off(&oldest);
+        }
+        for history in [&mut self.local_checksums, &mut self.remote_checksums] {
+            *history = history.split_off(&oldest);
+        }
+    }
+
+    fn check(&mut self, frame: u32) {
+        if self.desync.is_some() {
+            return;
+        }
+
+        if let (Some(local), Some(remote)) = (
+            self.local_checksums.get(&frame),
+            self.remote_checksums.get(&frame),
+        ) {
+            if local != remote {
+                self.desync = Some(frame);
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use std::{fs, io};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Reads the JSON file at `path`, the default value if it is missing or can't
+/// be parsed.
+pub fn load_json<T: DeserializeOwned + Default>(path: &str) -> T {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Writes `value` to the file at `path` as indented JSON.
+pub fn save_json<T: Serialize>(path: &str, value: &T) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    fs::write(path, json)
+}
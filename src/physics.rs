@@ -0,0 +1,273 @@
+use std::f32::consts::PI;
+
+use macroquad::prelude::Vec2;
+
+/// Tuning of the ship movement.
+///
+/// Speeds are in pixels per second, rotations in degrees per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShipPhysics {
+    /// Acceleration applied while thrusting.
+    pub thrust: f32,
+    /// Fraction of the velocity lost per second when drifting.
+    pub drag: f32,
+    /// Terminal velocity of the ship.
+    pub max_speed: f32,
+    /// Angular acceleration applied while turning.
+    pub rotation_acceleration: f32,
+    /// Maximum angular velocity.
+    pub max_rotation_speed: f32,
+    /// Fraction of the angular velocity lost per second when not turning.
+    pub rotation_drag: f32,
+    /// Disable the linear drag, the ship keeps drifting forever.
+    pub newtonian: bool,
+}
+
+impl Default for ShipPhysics {
+    fn default() -> Self {
+        Self {
+            thrust: 300.0,
+            drag: 0.5,
+            max_speed: 400.0,
+            rotation_acceleration: 1500.0,
+            max_rotation_speed: 250.0,
+            rotation_drag: 12.0,
+            newtonian: false,
+        }
+    }
+}
+
+impl ShipPhysics {
+    /// Returns the angular velocity after `dt` seconds, `turn` being the
+    /// steering input between -1.0 (left) and 1.0 (right).
+    pub fn rotate(&self, angular_velocity: f32, turn: f32, dt: f32) -> f32 {
+        let angular_velocity = if turn != 0.0 {
+            angular_velocity + turn.clamp(-1.0, 1.0) * self.rotation_acceleration * dt
+        } else {
+            angular_velocity * (1.0 - self.rotation_drag * dt).max(0.0)
+        };
+
+        angular_velocity.clamp(-self.max_rotation_speed, self.max_rotation_speed)
+    }
+
+    /// Returns the velocity after `dt` seconds for a ship facing `rotation`.
+    pub fn accelerate(&self, velocity: Vec2, rotation: f32, thrust: bool, dt: f32) -> Vec2 {
+        let mut velocity = velocity;
+
+        if thrust {
+            velocity += direction(rotation) * self.thrust * dt;
+        } else if !self.newtonian {
+            velocity *= (1.0 - self.drag * dt).max(0.0);
+        }
+
+        velocity.clamp_length_max(self.max_speed)
+    }
+}
+
+/// Unit vector pointing where an entity rotated by `rotation` degrees is
+/// facing, 0 being the top of the screen.
+pub fn direction(rotation: f32) -> Vec2 {
+    let angle = rotation / 180.0 * PI - PI / 2.0;
+
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+/// Brings a centre leaving the `bounds` back in from the opposite edge, the
+/// part of the entity still across the edge being drawn as a ghost.
+pub fn wrap(position: Vec2, bounds: Vec2) -> Vec2 {
+    Vec2::new(
+        position.x.rem_euclid(bounds.x),
+        position.y.rem_euclid(bounds.y),
+    )
+}
+
+/// Offsets at which to draw the ghosts of an entity of `radius` centred on
+/// `position`, for the parts of it crossing the edges of the `bounds`.
+pub fn ghosts(position: Vec2, radius: f32, bounds: Vec2) -> Vec<Vec2> {
+    let shift = |position: f32, bound: f32| {
+        if position < radius {
+            bound
+        } else if position > bound - radius {
+            -bound
+        } else {
+            0.0
+        }
+    };
+    let (x, y) = (shift(position.x, bounds.x), shift(position.y, bounds.y));
+
+    let mut offsets = vec![];
+    if x != 0.0 {
+        offsets.push(Vec2::new(x, 0.0));
+    }
+    if y != 0.0 {
+        offsets.push(Vec2::new(0.0, y));
+    }
+    // Near a corner, the ghost diagonally across too
+    if x != 0.0 && y != 0.0 {
+        offsets.push(Vec2::new(x, y));
+    }
+
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    const ARENA: Vec2 = Vec2::new(1280.0, 720.0);
+
+    /// Ship flown headless for some frames, the way the world moves it.
+    struct Flight {
+        physics: ShipPhysics,
+        position: Vec2,
+        velocity: Vec2,
+        rotation: f32,
+    }
+
+    impl Flight {
+        fn new(physics: ShipPhysics, velocity: Vec2) -> Self {
+            Self {
+                physics,
+                position: ARENA / 2.0,
+                velocity,
+                rotation: 0.0,
+            }
+        }
+
+        fn step(&mut self, frames: usize, thrust: bool) {
+            for _ in 0..frames {
+                self.velocity = self
+                    .physics
+                    .accelerate(self.velocity, self.rotation, thrust, DT);
+                self.position = wrap(self.position + self.velocity * DT, ARENA);
+            }
+        }
+    }
+
+    #[test]
+    fn drag_brings_ship_to_rest() {
+        let physics = ShipPhysics::default();
+        let mut velocity = Vec2::new(300.0, -200.0);
+        let mut previous = velocity.length();
+        for _ in 0..60 * 30 {
+            velocity = physics.accelerate(velocity, 0.0, false, DT);
+            assert!(velocity.length() <= previous);
+            previous = velocity.length();
+        }
+
+        assert!(velocity.length() < 0.01);
+    }
+
+    #[test]
+    fn drifting_ship_coasts_to_a_stop() {
+        let mut flight = Flight::new(ShipPhysics::default(), Vec2::new(200.0, 0.0));
+        flight.step(60 * 20, false);
+        let stopped = flight.position;
+        flight.step(60, false);
+
+        // Never comes back around, the drag wins well before a full lap
+        assert!(stopped.x > ARENA.x / 2.0 && stopped.x < ARENA.x);
+        assert!((stopped.y - ARENA.y / 2.0).abs() < 0.001);
+        assert!(flight.position.distance(stopped) < 0.01);
+    }
+
+    #[test]
+    fn newtonian_ship_keeps_a_straight_course() {
+        let physics = ShipPhysics {
+            newtonian: true,
+            ..Default::default()
+        };
+        let velocity = Vec2::new(130.0, -70.0);
+        let mut flight = Flight::new(physics, velocity);
+
+        for second in 1..=10 {
+            flight.step(60, false);
+            let expected = wrap(ARENA / 2.0 + velocity * second as f32, ARENA);
+            assert!(
+                flight.position.distance(expected) < 0.5,
+                "at {}s expected {} got {}",
+                second,
+                expected,
+                flight.position
+            );
+            assert_eq!(flight.velocity.length(), velocity.length());
+        }
+    }
+
+    #[test]
+    fn thrusting_ship_wraps_around() {
+        // Facing up, leaving at the top and coming back from the bottom
+        let mut flight = Flight::new(ShipPhysics::default(), Vec2::ZERO);
+        let mut wrapped = false;
+        let mut previous = flight.position;
+        for _ in 0..60 * 3 {
+            flight.step(1, true);
+            assert!(flight.position.x == ARENA.x / 2.0);
+            assert!(flight.position.y >= 0.0 && flight.position.y < ARENA.y);
+            wrapped |= flight.position.y > previous.y;
+            previous = flight.position;
+        }
+
+        assert!(wrapped);
+    }
+
+    #[test]
+    fn thrust_is_clamped_to_max_speed() {
+        let physics = ShipPhysics::default();
+        let mut velocity = Vec2::ZERO;
+        for _ in 0..60 * 10 {
+            velocity = physics.accelerate(velocity, 90.0, true, DT);
+            assert!(velocity.length() <= physics.max_speed + 0.001);
+        }
+
+        assert!((velocity.length() - physics.max_speed).abs() < 0.001);
+        // Facing right
+        assert!(velocity.x > 0.0 && velocity.y.abs() < 0.001);
+    }
+
+    #[test]
+    fn newtonian_ship_coasts() {
+        let physics = ShipPhysics {
+            newtonian: true,
+            ..Default::default()
+        };
+        let start = Vec2::new(120.0, 45.0);
+        let mut velocity = start;
+        for _ in 0..60 * 10 {
+            velocity = physics.accelerate(velocity, 0.0, false, DT);
+        }
+
+        assert_eq!(velocity, start);
+    }
+
+    #[test]
+    fn rotation_is_clamped_to_max_rotation_speed() {
+        let physics = ShipPhysics::default();
+        let (mut right, mut left) = (0.0, 0.0);
+        for _ in 0..60 * 2 {
+            right = physics.rotate(right, 1.0, DT);
+            left = physics.rotate(left, -1.0, DT);
+        }
+
+        assert_eq!(right, physics.max_rotation_speed);
+        assert_eq!(left, -physics.max_rotation_speed);
+        // Steering beyond full lock turns no faster
+        assert_eq!(physics.rotate(0.0, 5.0, DT), physics.rotate(0.0, 1.0, DT));
+    }
+
+    #[test]
+    fn rotation_drag_stops_turning() {
+        let physics = ShipPhysics::default();
+        let mut angular_velocity = physics.max_rotation_speed;
+        let mut previous = angular_velocity;
+        for _ in 0..60 {
+            angular_velocity = physics.rotate(angular_velocity, 0.0, DT);
+            assert!(angular_velocity.abs() <= previous.abs());
+            previous = angular_velocity;
+        }
+
+        assert!(angular_velocity.abs() < 0.01);
+    }
+}
@@ -0,0 +1,400 @@
+use log::{error, info, warn};
+use macroquad::{
+    miniquad::{gl, PassAction},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+/// Post-processing effects, applied together in a single pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Effects {
+    /// Scanlines, curved glass and darker corners of a CRT monitor.
+    pub crt: bool,
+    /// Light bleeding around bright lines.
+    pub bloom: bool,
+    /// Colour fringes towards the edges of the screen.
+    pub chromatic: bool,
+    /// Camera shaking on explosions.
+    pub shake: bool,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            crt: false,
+            bloom: true,
+            chromatic: false,
+            shake: true,
+        }
+    }
+}
+
+impl Effects {
+    /// Whether the frame has to go through the post-processing pass.
+    fn any(&self) -> bool {
+        self.crt || self.bloom || self.chromatic
+    }
+}
+
+/// Where states draw: the screen, or the render target of the
+/// post-processing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Canvas {
+    pub target: Option<RenderTarget>,
+}
+
+impl Canvas {
+    /// Draws in screen pixels from now on.
+    pub fn set_screen(&self) {
+        match self.target {
+            Some(target) => {
+                let mut camera = Camera2D::from_display_rect(Rect::new(
+                    0.0,
+                    0.0,
+                    screen_width(),
+                    screen_height(),
+                ));
+                camera.render_target = Some(target);
+                set_camera(&camera);
+            }
+            None => set_default_camera(),
+        }
+    }
+
+    /// Makes `camera` draw on the canvas.
+    pub fn set_camera(&self, mut camera: Camera2D) {
+        camera.render_target = self.target;
+        set_camera(&camera);
+    }
+}
+
+/// Why a frame goes straight to the screen rather than through the effects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Bypass {
+    /// The shader could not be built, or the render target drawn on.
+    Disabled,
+    /// Every effect is off.
+    Off,
+    /// The window has no size, like when minimised.
+    NoSize,
+}
+
+/// Whether a frame of `width` by `height` pixels goes through the `effects`,
+/// `available` when the shader and render target work.
+fn route(available: bool, effects: &Effects, width: u32, height: u32) -> Result<(), Bypass> {
+    if !available {
+        Err(Bypass::Disabled)
+    } else if !effects.any() {
+        Err(Bypass::Off)
+    } else if width == 0 || height == 0 {
+        Err(Bypass::NoSize)
+    } else {
+        Ok(())
+    }
+}
+
+/// Renders the frame to a texture, then to the screen through the effects.
+pub struct PostProcess {
+    /// None when the shader could not be built or the render target could
+    /// not be drawn on, the frame then goes straight to the screen.
+    material: Option<Material>,
+    target: Option<RenderTarget>,
+    /// Path the last frame took, logged when it changes.
+    path: Option<Result<(), Bypass>>,
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        let material = load_material(
+            VERTEX_SHADER,
+            FRAGMENT_SHADER,
+            MaterialParams {
+                uniforms: vec![
+                    ("resolution".to_string(), UniformType::Float2),
+                    ("crt".to_string(), UniformType::Float1),
+                    ("bloom".to_string(), UniformType::Float1),
+                    ("chromatic".to_string(), UniformType::Float1),
+                ],
+                ..Default::default()
+            },
+        );
+
+        let material = match material {
+            Ok(material) => Some(material),
+            Err(error) => {
+                warn!("post-processing disabled error={:?}", error);
+                None
+            }
+        };
+
+        Self {
+            material,
+            target: None,
+            path: None,
+        }
+    }
+
+    /// Starts a frame, returning the canvas to draw it on.
+    pub fn begin(&mut self, effects: &Effects) -> Canvas {
+        let (width, height) = (screen_width() as u32, screen_height() as u32);
+        let mut path = route(self.material.is_some(), effects, width, height);
+        let target = match path {
+            Ok(()) => self.target(width, height),
+            Err(_) => None,
+        };
+        if path.is_ok() && target.is_none() {
+            path = Err(Bypass::Disabled);
+        }
+        if self.path != Some(path) {
+            info!("post-processing path={:?}", path);
+            self.path = Some(path);
+        }
+
+        let Some(target) = target else {
+            set_default_camera();
+            return Canvas::default();
+        };
+
+        let canvas = Canvas {
+            target: Some(target),
+        };
+        canvas.set_screen();
+        clear_background(BLACK);
+
+        canvas
+    }
+
+    /// Render target of `width` by `height` pixels, made again when the
+    /// window is resized. None when the driver can not draw on it,
+    /// post-processing being disabled then.
+    fn target(&mut self, width: u32, height: u32) -> Option<RenderTarget> {
+        if let Some(target) = self.target {
+            if target.texture.width() as u32 == width && target.texture.height() as u32 == height {
+                return Some(target);
+            }
+
+            target.delete();
+            self.target = None;
+        }
+
+        let target = render_target(width, height);
+        if let Err(status) = check(target) {
+            error!(
+                "post-processing disabled error=incomplete render target status={:#x} size={}x{}",
+                status, width, height
+            );
+            target.delete();
+            self.material = None;
+            return None;
+        }
+
+        target.texture.set_filter(FilterMode::Linear);
+        self.target = Some(target);
+
+        Some(target)
+    }
+
+    /// Draws the frame to the screen through the enabled effects.
+    pub fn end(&self, canvas: &Canvas, effects: &Effects) {
+        set_default_camera();
+
+        let (Some(material), Some(target)) = (self.material, canvas.target) else {
+            return;
+        };
+
+        let flag = |enabled: bool| if enabled { 1.0f32 } else { 0.0 };
+        material.set_uniform("resolution", vec2(screen_width(), screen_height()));
+        material.set_uniform("crt", flag(effects.crt));
+        material.set_uniform("bloom", flag(effects.bloom));
+        material.set_uniform("chromatic", flag(effects.chromatic));
+
+        gl_use_material(material);
+        draw_texture_ex(
+            target.texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(screen_width(), screen_height())),
+                // Render targets are stored upside down
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+    }
+}
+
+/// Asks the driver whether `target` can be drawn on, returning the status of
+/// its framebuffer if not.
+fn check(target: RenderTarget) -> Result<(), u32> {
+    let mut context = unsafe { get_internal_gl() };
+    context.flush();
+
+    context
+        .quad_context
+        .begin_pass(target.render_pass, PassAction::Nothing);
+    let status = unsafe { gl::glCheckFramebufferStatus(gl::GL_FRAMEBUFFER) };
+    context.quad_context.end_render_pass();
+
+    if status == gl::GL_FRAMEBUFFER_COMPLETE {
+        Ok(())
+    } else {
+        Err(status)
+    }
+}
+
+/// Shaking of the camera, growing with explosions and fading over time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Shake {
+    /// Between 0.0 and 1.0, the offset grows with its square.
+    trauma: f32,
+    /// Seconds the shake has been updated for, so it stops with the game.
+    time: f32,
+}
+
+impl Shake {
+    /// Offset in virtual pixels at full trauma.
+    const MAX_OFFSET: f32 = 12.0;
+    /// Trauma lost per second.
+    const DECAY: f32 = 1.5;
+
+    pub fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.trauma = (self.trauma - Self::DECAY * dt).max(0.0);
+        self.time += dt;
+    }
+
+    /// Offset of the camera this frame, from its own clock rather than the
+    /// random generator so that online games stay in sync.
+    pub fn offset(&self) -> Vec2 {
+        let time = self.time;
+        let noise = Vec2::new((time * 53.0).sin(), (time * 47.0 + 1.3).cos());
+
+        noise * self.trauma * self.trauma * Self::MAX_OFFSET
+    }
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 100
+precision mediump float;
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform sampler2D Texture;
+uniform vec2 resolution;
+uniform float crt;
+uniform float bloom;
+uniform float chromatic;
+
+vec3 fetch(vec2 at) {
+    if (chromatic > 0.5) {
+        vec2 fringe = (at - 0.5) * 0.006;
+        return vec3(
+            texture2D(Texture, at - fringe).r,
+            texture2D(Texture, at).g,
+            texture2D(Texture, at + fringe).b
+        );
+    }
+
+    return texture2D(Texture, at).rgb;
+}
+
+void main() {
+    vec2 at = uv;
+
+    // Bulge of the glass
+    if (crt > 0.5) {
+        vec2 centered = at * 2.0 - 1.0;
+        centered *= 1.0 + dot(centered.yx, centered.yx) * vec2(0.03, 0.04);
+        at = centered * 0.5 + 0.5;
+        if (at.x < 0.0 || at.x > 1.0 || at.y < 0.0 || at.y > 1.0) {
+            gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+    }
+
+    vec3 result = fetch(at);
+
+    // Bright parts bleeding on their neighbours
+    if (bloom > 0.5) {
+        vec2 texel = 1.0 / resolution;
+        vec3 glow = vec3(0.0);
+        for (int x = -2; x <= 2; x++) {
+            for (int y = -2; y <= 2; y++) {
+                vec2 offset = vec2(float(x), float(y)) * texel * 2.0;
+                glow += max(texture2D(Texture, at + offset).rgb - 0.5, 0.0);
+            }
+        }
+        result += glow / 25.0 * 2.5;
+    }
+
+    if (crt > 0.5) {
+        result *= 0.85 + 0.15 * sin(at.y * resolution.y * 3.14159);
+        vec2 corner = at * (1.0 - at.yx);
+        result *= pow(corner.x * corner.y * 15.0, 0.25);
+    }
+
+    gl_FragColor = vec4(result, 1.0) * color;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONE: Effects = Effects {
+        crt: false,
+        bloom: false,
+        chromatic: false,
+        shake: true,
+    };
+
+    #[test]
+    fn goes_through_the_effects_when_it_can() {
+        let effects = Effects { crt: true, ..NONE };
+
+        assert_eq!(route(true, &effects, 1280, 720), Ok(()));
+        assert_eq!(route(true, &Effects::default(), 1280, 720), Ok(()));
+    }
+
+    #[test]
+    fn falls_back_to_the_screen() {
+        let effects = Effects::default();
+
+        assert_eq!(route(false, &effects, 1280, 720), Err(Bypass::Disabled));
+        assert_eq!(route(true, &NONE, 1280, 720), Err(Bypass::Off));
+        assert_eq!(route(true, &effects, 0, 720), Err(Bypass::NoSize));
+        assert_eq!(route(true, &effects, 1280, 0), Err(Bypass::NoSize));
+        // Broken is broken, whatever the effects
+        assert_eq!(route(false, &NONE, 0, 0), Err(Bypass::Disabled));
+    }
+}
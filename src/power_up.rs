@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::{rand, Color, BLUE, GOLD, GREEN, ORANGE, PINK, RED};
+use serde::{Deserialize, Serialize};
+
+use crate::weapon::Weapon;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    SpreadShot,
+    RapidFire,
+    Piercing,
+    Shield,
+    ExtraLife,
+    SmartBomb,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::SpreadShot,
+        PowerUpKind::RapidFire,
+        PowerUpKind::Piercing,
+        PowerUpKind::Shield,
+        PowerUpKind::ExtraLife,
+        PowerUpKind::SmartBomb,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PowerUpKind::SpreadShot => "SPREAD",
+            PowerUpKind::RapidFire => "RAPID",
+            PowerUpKind::Piercing => "PIERCE",
+            PowerUpKind::Shield => "SHIELD",
+            PowerUpKind::ExtraLife => "1UP",
+            PowerUpKind::SmartBomb => "BOMB",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUpKind::SpreadShot => ORANGE,
+            PowerUpKind::RapidFire => GOLD,
+            PowerUpKind::Piercing => PINK,
+            PowerUpKind::Shield => BLUE,
+            PowerUpKind::ExtraLife => GREEN,
+            PowerUpKind::SmartBomb => RED,
+        }
+    }
+}
+
+/// Timed upgrades currently granted to a ship.
+#[derive(Default)]
+pub struct PowerUps {
+    timers: HashMap<PowerUpKind, f32>,
+}
+
+impl PowerUps {
+    /// Grants `kind` for `duration` seconds, picking the same upgrade again
+    /// restarts its timer.
+    pub fn grant(&mut self, kind: PowerUpKind, duration: f32) {
+        self.timers.insert(kind, duration);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for remaining in self.timers.values_mut() {
+            *remaining -= dt;
+        }
+
+        self.timers.retain(|_, remaining| *remaining > 0.0);
+    }
+
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.timers.contains_key(&kind)
+    }
+
+    /// Active upgrades with their remaining time, in a stable order.
+    pub fn active(&self) -> Vec<(PowerUpKind, f32)> {
+        PowerUpKind::ALL
+            .iter()
+            .filter_map(|kind| self.timers.get(kind).map(|remaining| (*kind, *remaining)))
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+
+    /// Applies the active upgrades on top of the base `weapon`.
+    pub fn apply(&self, weapon: &Weapon) -> Weapon {
+        let mut weapon = *weapon;
+
+        if self.is_active(PowerUpKind::RapidFire) {
+            weapon.auto_cooldown /= 3.0;
+            weapon.tap_cooldown /= 3.0;
+            weapon.max_shots = weapon.max_shots.saturating_mul(2);
+        }
+
+        if self.is_active(PowerUpKind::SpreadShot) {
+            weapon.spread_shots = 3;
+        }
+
+        if self.is_active(PowerUpKind::Piercing) {
+            weapon.piercing = true;
+        }
+
+        weapon
+    }
+}
+
+/// Chance for a destroyed meteor or saucer to leave a power-up behind.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DropTable {
+    /// Probability between 0.0 and 1.0 that something drops at all.
+    pub chance: f32,
+    /// Relative weight of each kind once a drop happens.
+    pub weights: Vec<(PowerUpKind, f32)>,
+    /// Time in seconds a dropped power-up stays before vanishing.
+    pub lifetime: f32,
+}
+
+impl DropTable {
+    pub fn roll(&self) -> Option<PowerUpKind> {
+        if rand::gen_range(0.0, 1.0) >= self.chance {
+            return None;
+        }
+
+        let total: f32 = self.weights.iter().map(|(_, weight)| weight).sum();
+        let mut pick = rand::gen_range(0.0, total);
+
+        for (kind, weight) in self.weights.iter() {
+            if pick < *weight {
+                return Some(*kind);
+            }
+
+            pick -= weight;
+        }
+
+        None
+    }
+}
+
+/// Tuning of the power-ups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PowerUpConfig {
+    /// Time in seconds a timed upgrade lasts.
+    pub duration: f32,
+    /// Time in seconds a dropped power-up stays before vanishing.
+    pub lifetime: f32,
+    /// Drop chance for each level, the last one being used past it.
+    pub drop_chances: Vec<f32>,
+    pub weights: Vec<(PowerUpKind, f32)>,
+}
+
+impl Default for PowerUpConfig {
+    fn default() -> Self {
+        Self {
+            duration: 10.0,
+            lifetime: 8.0,
+            drop_chances: vec![0.05, 0.06, 0.08, 0.1, 0.12],
+            weights: vec![
+                (PowerUpKind::SpreadShot, 3.0),
+                (PowerUpKind::RapidFire, 3.0),
+                (PowerUpKind::Piercing, 2.0),
+                (PowerUpKind::Shield, 2.0),
+                (PowerUpKind::ExtraLife, 1.0),
+                (PowerUpKind::SmartBomb, 1.0),
+            ],
+        }
+    }
+}
+
+impl PowerUpConfig {
+    /// Drop table of the 1-based `level`.
+    pub fn drop_table(&self, level: u32) -> DropTable {
+        let index = (level.max(1) as usize - 1).min(self.drop_chances.len().saturating_sub(1));
+
+        DropTable {
+            chance: self.drop_chances.get(index).copied().unwrap_or_default(),
+            weights: self.weights.clone(),
+            lifetime: self.lifetime,
+        }
+    }
+}
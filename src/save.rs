@@ -0,0 +1,93 @@
+use std::{fs, io, path::Path};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    daily::Date, entity::EntitySave, game_mode::GameMode, persist, power_up::PowerUpKind,
+    state_manager::PlayerMode, stats::Stats,
+};
+
+/// Version of the save file format, saves of another version are ignored.
+pub const VERSION: u32 = 2;
+
+const PATH: &str = "save.json";
+
+/// Entities of a wave with the level it belongs to.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WaveSave {
+    pub level: u32,
+    pub entities: Vec<EntitySave>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSlotSave {
+    pub lives: u8,
+    pub score: u32,
+    /// Number of the wave the player is on.
+    #[serde(default)]
+    pub waves: u32,
+    pub power_ups: Vec<(PowerUpKind, f32)>,
+    /// Wave put aside while the other player takes their turn.
+    pub wave: Option<WaveSave>,
+}
+
+/// Game in progress, written when quitting to resume it later.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub mode: PlayerMode,
+    #[serde(default)]
+    pub game_mode: GameMode,
+    /// Date of the daily challenge being played.
+    #[serde(default)]
+    pub date: Option<Date>,
+    /// Seconds played, for the timed and survival modes.
+    #[serde(default)]
+    pub elapsed: f32,
+    /// Player whose turn it is in alternating mode.
+    pub active: usize,
+    pub players: Vec<PlayerSlotSave>,
+    pub wave: WaveSave,
+    /// Stats of the game so far, missing from saves written before they were
+    /// tracked.
+    #[serde(default)]
+    pub stats: Stats,
+}
+
+pub fn exists() -> bool {
+    Path::new(PATH).exists()
+}
+
+pub fn write(save: &SaveFile) -> io::Result<()> {
+    persist::save_json(PATH, save)
+}
+
+/// Reads the save file, if there is one of the current version.
+pub fn read() -> Option<SaveFile> {
+    let json = fs::read_to_string(PATH).ok()?;
+    let save: SaveFile = serde_json::from_str(&json).ok()?;
+
+    (save.version == VERSION).then_some(save)
+}
+
+/// Whether there is a game to resume, deleting a save file which can't be
+/// read anymore, like one of an older version.
+pub fn is_resumable() -> bool {
+    if !exists() {
+        return false;
+    }
+    if read().is_some() {
+        return true;
+    }
+
+    warn!("unreadable save removed path={}", PATH);
+    remove();
+
+    false
+}
+
+/// Deletes the save file once the game it holds is over.
+pub fn remove() {
+    let _ = fs::remove_file(PATH);
+}
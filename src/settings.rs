@@ -0,0 +1,56 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::Aspect, persist, post::Effects, starfield::Background, weapon::FireMode};
+
+const PATH: &str = "settings.json";
+
+/// How entities are drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderStyle {
+    /// Textures of the `res` folder.
+    #[default]
+    Sprites,
+    /// Glowing outlines, like the 1979 arcade cabinet.
+    Vector,
+}
+
+impl RenderStyle {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RenderStyle::Sprites => "Sprites",
+            RenderStyle::Vector => "Vector",
+        }
+    }
+
+    pub fn next(&self) -> RenderStyle {
+        match self {
+            RenderStyle::Sprites => RenderStyle::Vector,
+            RenderStyle::Vector => RenderStyle::Sprites,
+        }
+    }
+}
+
+/// Preferences of the player, kept between runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub style: RenderStyle,
+    pub aspect: Aspect,
+    pub effects: Effects,
+    pub background: Background,
+    /// What the fire key does, in local games.
+    pub fire_mode: FireMode,
+}
+
+impl Settings {
+    /// Reads the settings, the defaults if they were never saved.
+    pub fn load() -> Self {
+        persist::load_json(PATH)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        persist::save_json(PATH, self)
+    }
+}
@@ -0,0 +1,207 @@
+use macroquad::prelude::*;
+
+use crate::{collision, physics};
+
+/// Positions drawn for each spawn, the first clear one is kept or else the
+/// farthest from the ships, pushed out of their safe radius.
+const ATTEMPTS: usize = 32;
+
+/// Where meteors appear at the start of a wave and during it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpawnConfig {
+    /// Distance in pixels to keep between a new meteor and every ship.
+    pub safe_radius: f32,
+    /// Meteors appear within this distance in pixels of an edge of the arena.
+    pub edge_margin: f32,
+    /// Time in seconds a mid-wave spawn is announced before the meteor
+    /// appears.
+    pub warning_time: f32,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        Self {
+            safe_radius: 150.0,
+            edge_margin: 60.0,
+            warning_time: 1.5,
+        }
+    }
+}
+
+impl SpawnConfig {
+    /// Picks a random position along the edges of `arena` for a meteor of
+    /// `radius`, which stays outside the safe radius around each of the
+    /// `ships`, across the edges too.
+    ///
+    /// As many random numbers are drawn wherever the ships are, so that seeded
+    /// waves stay the same.
+    pub fn position(&self, arena: Vec2, ships: &[Vec2], radius: f32) -> Vec2 {
+        // Gap between the ships and the edge of the meteor
+        let clearance = |position: &Vec2| {
+            ships
+                .iter()
+                .map(|ship| collision::wrapped_delta(*ship, *position, arena).length() - radius)
+                .fold(f32::MAX, f32::min)
+        };
+
+        let candidates: Vec<Vec2> = (0..ATTEMPTS).map(|_| self.edge_position(arena)).collect();
+        if let Some(position) = candidates
+            .iter()
+            .find(|position| clearance(position) > self.safe_radius)
+        {
+            return *position;
+        }
+
+        let position = candidates
+            .into_iter()
+            .max_by(|a, b| clearance(a).total_cmp(&clearance(b)))
+            .unwrap();
+
+        self.push_out(position, arena, ships, radius)
+    }
+
+    /// Moves `position` straight away from each of the `ships` it is too close
+    /// to, until the meteor of `radius` clears their safe radius.
+    pub fn push_out(&self, mut position: Vec2, arena: Vec2, ships: &[Vec2], radius: f32) -> Vec2 {
+        // A tiny margin so rounding never leaves it right on the radius
+        let distance = self.safe_radius + radius + 1.0;
+
+        // Pushed away from one ship, it may come too close to another
+        for _ in 0..ATTEMPTS {
+            let mut moved = false;
+            for ship in ships.iter() {
+                let delta = collision::wrapped_delta(*ship, position, arena);
+                if delta.length() >= distance {
+                    continue;
+                }
+
+                // Right on the ship, any way out will do
+                let direction = delta.try_normalize().unwrap_or(Vec2::Y);
+                position = physics::wrap(*ship + direction * distance, arena);
+                moved = true;
+            }
+
+            if !moved {
+                break;
+            }
+        }
+
+        position
+    }
+
+    fn edge_position(&self, arena: Vec2) -> Vec2 {
+        let margin = self.edge_margin.min(arena.min_element() * 0.5);
+        let along = |length: f32| rand::gen_range(0.0, length);
+        let across = |length: f32| match rand::gen_range(0, 2) {
+            0 => rand::gen_range(0.0, margin),
+            _ => length - rand::gen_range(0.0, margin),
+        };
+
+        match rand::gen_range(0, 2) {
+            0 => vec2(along(arena.x), across(arena.y)),
+            _ => vec2(across(arena.x), along(arena.y)),
+        }
+    }
+}
+
+/// Meteor about to appear mid-wave, announced to the players.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Warning {
+    pub position: Vec2,
+    /// Time in seconds before the meteor appears.
+    pub time_left: f32,
+}
+
+impl Warning {
+    /// Draws a blinking marker where the meteor will appear, kept inside the
+    /// `arena`.
+    pub fn draw(&self, arena: Vec2) {
+        if (self.time_left * 6.0) as i32 % 2 == 1 {
+            return;
+        }
+
+        let radius = 20.0;
+        let x = self.position.x.clamp(radius, arena.x - radius);
+        let y = self.position.y.clamp(radius, arena.y - radius);
+
+        draw_circle_lines(x, y, radius, 3.0, RED);
+        draw_text("!", x - 4.0, y + 8.0, 28.0, RED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena;
+
+    const ARENA: Vec2 = arena::SIZE;
+
+    const RADIUS: f32 = 45.0;
+
+    /// Checks that every position picked around `ships` clears them.
+    fn assert_clear(ships: &[Vec2]) {
+        let config = SpawnConfig::default();
+        rand::srand(42);
+
+        for _ in 0..500 {
+            let position = config.position(ARENA, ships, RADIUS);
+            for ship in ships.iter() {
+                let distance = collision::wrapped_delta(*ship, position, ARENA).length();
+                assert!(
+                    distance - RADIUS > config.safe_radius,
+                    "meteor at {} too close to ship at {}",
+                    position,
+                    ship
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn clears_ship_in_the_centre() {
+        assert_clear(&[ARENA / 2.0]);
+    }
+
+    #[test]
+    fn clears_ship_on_an_edge() {
+        assert_clear(&[vec2(ARENA.x / 2.0, 5.0)]);
+        assert_clear(&[vec2(ARENA.x - 5.0, ARENA.y / 2.0)]);
+    }
+
+    #[test]
+    fn clears_ship_in_a_corner() {
+        // Across the edges, every corner is next to this one
+        assert_clear(&[vec2(5.0, 5.0)]);
+        assert_clear(&[vec2(ARENA.x - 5.0, ARENA.y - 5.0)]);
+    }
+
+    #[test]
+    fn pushes_out_when_the_edges_are_crowded() {
+        // No position drawn along the edges is clear
+        let mut ships = vec![];
+        for x in (0..ARENA.x as i32).step_by(100) {
+            ships.push(vec2(x as f32, 0.0));
+        }
+        for y in (0..ARENA.y as i32).step_by(100) {
+            ships.push(vec2(0.0, y as f32));
+        }
+
+        assert_clear(&ships);
+    }
+
+    #[test]
+    fn pushes_out_of_ships_that_came_close() {
+        let config = SpawnConfig::default();
+        let ship = ARENA / 2.0;
+
+        for position in [ship, ship + vec2(10.0, 0.0), vec2(ship.x, 5.0)] {
+            let position = config.push_out(position, ARENA, &[ship], RADIUS);
+            let distance = collision::wrapped_delta(ship, position, ARENA).length();
+            assert!(distance - RADIUS > config.safe_radius);
+        }
+
+        // Already clear, it stays put
+        let clear = vec2(5.0, 5.0);
+        assert_eq!(config.push_out(clear, ARENA, &[ship], RADIUS), clear);
+    }
+}
@@ -0,0 +1,255 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics;
+
+/// What is drawn behind the entities.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Background {
+    /// Drifting stars, nebulae and the odd comet.
+    #[default]
+    Stars,
+    /// Plain black, the cheapest to draw.
+    Off,
+}
+
+impl Background {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Background::Stars => "Stars",
+            Background::Off => "Off",
+        }
+    }
+
+    pub fn next(&self) -> Background {
+        match self {
+            Background::Stars => Background::Off,
+            Background::Off => Background::Stars,
+        }
+    }
+}
+
+/// Layers of stars from the farthest to the nearest, as star count, fraction of
+/// the ships' velocity they drift by, and size.
+const LAYERS: [(usize, f32, f32); 3] = [(90, 0.05, 1.0), (45, 0.12, 1.5), (20, 0.25, 2.0)];
+
+/// Drift of every layer with still ships, so the sky never freezes.
+const DRIFT: Vec2 = Vec2::new(-4.0, 1.5);
+
+const NEBULAE: usize = 3;
+
+/// Seconds between two comets, at least and at most.
+const COMET_INTERVAL: (f32, f32) = (12.0, 30.0);
+
+/// Random numbers of the background, apart from the game's own so that drawing
+/// it leaves online and daily games untouched.
+struct Noise(u64);
+
+impl Noise {
+    /// Between 0.0 and 1.0.
+    fn next(&mut self) -> f32 {
+        // xorshift64*
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next()
+    }
+
+    /// Point of a square of side 1, scaled to the arena when drawn.
+    fn point(&mut self) -> Vec2 {
+        vec2(self.next(), self.next())
+    }
+}
+
+struct Star {
+    position: Vec2,
+    brightness: f32,
+    /// Phase of the twinkling, in radians.
+    phase: f32,
+}
+
+struct Layer {
+    stars: Vec<Star>,
+    parallax: f32,
+    size: f32,
+    offset: Vec2,
+}
+
+struct Nebula {
+    position: Vec2,
+    radius: f32,
+    color: Color,
+}
+
+struct Comet {
+    position: Vec2,
+    velocity: Vec2,
+}
+
+/// Sky behind the arena, drifting against the ships' velocity with the
+/// nearest layers moving the most.
+pub struct Starfield {
+    layers: Vec<Layer>,
+    nebulae: Vec<Nebula>,
+    /// Offset of the nebulae, drifting slower than the farthest stars.
+    nebulae_offset: Vec2,
+    comet: Option<Comet>,
+    comet_timer: f32,
+    time: f32,
+    noise: Noise,
+}
+
+impl Starfield {
+    /// Sky always the same for a given `seed`.
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves zero
+        let mut noise = Noise(seed | 1);
+
+        let layers = LAYERS
+            .iter()
+            .map(|&(count, parallax, star_size)| Layer {
+                stars: (0..count)
+                    .map(|_| Star {
+                        position: noise.point(),
+                        brightness: noise.range(0.3, 1.0),
+                        phase: noise.range(0.0, std::f32::consts::TAU),
+                    })
+                    .collect(),
+                parallax,
+                size: star_size,
+                offset: Vec2::ZERO,
+            })
+            .collect();
+
+        let nebulae = (0..NEBULAE)
+            .map(|_| Nebula {
+                position: noise.point(),
+                radius: noise.range(80.0, 180.0),
+                color: Color::new(
+                    noise.range(0.2, 0.5),
+                    noise.range(0.1, 0.3),
+                    noise.range(0.4, 0.7),
+                    1.0,
+                ),
+            })
+            .collect();
+
+        let comet_timer = noise.range(COMET_INTERVAL.0, COMET_INTERVAL.1);
+
+        Self {
+            layers,
+            nebulae,
+            nebulae_offset: Vec2::ZERO,
+            comet: None,
+            comet_timer,
+            time: 0.0,
+            noise,
+        }
+    }
+
+    /// Moves the sky over an arena of `bounds` against `velocity`, the mean
+    /// velocity of the ships.
+    pub fn update(&mut self, dt: f32, velocity: Vec2, bounds: Vec2) {
+        self.time += dt;
+
+        let drift = DRIFT - velocity;
+        for layer in self.layers.iter_mut() {
+            layer.offset = physics::wrap(layer.offset + drift * layer.parallax * dt, bounds);
+        }
+        self.nebulae_offset =
+            physics::wrap(self.nebulae_offset + drift * LAYERS[0].1 * 0.5 * dt, bounds);
+
+        match self.comet.as_mut() {
+            Some(comet) => {
+                comet.position += comet.velocity * dt;
+                let margin = 100.0;
+                if comet.position.x < -margin
+                    || comet.position.y < -margin
+                    || comet.position.x > bounds.x + margin
+                    || comet.position.y > bounds.y + margin
+                {
+                    self.comet = None;
+                }
+            }
+            None => {
+                self.comet_timer -= dt;
+                if self.comet_timer <= 0.0 {
+                    self.comet = Some(self.launch_comet(bounds));
+                    self.comet_timer = self.noise.range(COMET_INTERVAL.0, COMET_INTERVAL.1);
+                }
+            }
+        }
+    }
+
+    /// Comet entering from the top or left edge, crossing towards the middle.
+    fn launch_comet(&mut self, bounds: Vec2) -> Comet {
+        let position = if self.noise.next() < 0.5 {
+            vec2(self.noise.next() * bounds.x, -50.0)
+        } else {
+            vec2(-50.0, self.noise.next() * bounds.y)
+        };
+        let target = bounds * vec2(self.noise.range(0.3, 0.7), self.noise.range(0.3, 0.7));
+        let speed = self.noise.range(250.0, 400.0);
+
+        Comet {
+            position,
+            velocity: (target - position).normalize_or_zero() * speed,
+        }
+    }
+
+    /// Draws the sky over an arena of `bounds`, once its camera is set.
+    pub fn draw(&self, bounds: Vec2) {
+        for nebula in self.nebulae.iter() {
+            let draw = |center: Vec2| {
+                // Soft edges from stacked translucent discs
+                for step in 1..=4 {
+                    let radius = nebula.radius * step as f32 / 4.0;
+                    let color = Color::new(nebula.color.r, nebula.color.g, nebula.color.b, 0.025);
+                    draw_circle(center.x, center.y, radius, color);
+                }
+            };
+
+            // Across the edges too, so it never pops from one side to the other
+            let center = physics::wrap(nebula.position * bounds + self.nebulae_offset, bounds);
+            draw(center);
+            for offset in physics::ghosts(center, nebula.radius, bounds) {
+                draw(center + offset);
+            }
+        }
+
+        for layer in self.layers.iter() {
+            for star in layer.stars.iter() {
+                let position = physics::wrap(star.position * bounds + layer.offset, bounds);
+                let twinkle = 0.8 + 0.2 * (self.time * 2.0 + star.phase).sin();
+                let alpha = star.brightness * twinkle;
+                draw_rectangle(
+                    position.x - layer.size * 0.5,
+                    position.y - layer.size * 0.5,
+                    layer.size,
+                    layer.size,
+                    Color::new(1.0, 1.0, 1.0, alpha),
+                );
+            }
+        }
+
+        if let Some(comet) = self.comet.as_ref() {
+            let tail = comet.position - comet.velocity.normalize_or_zero() * 60.0;
+            for (thickness, alpha) in [(4.0, 0.15), (2.0, 0.4), (1.0, 0.9)] {
+                draw_line(
+                    tail.x,
+                    tail.y,
+                    comet.position.x,
+                    comet.position.y,
+                    thickness,
+                    Color::new(0.8, 0.9, 1.0, alpha),
+                );
+            }
+            draw_circle(comet.position.x, comet.position.y, 2.0, WHITE);
+        }
+    }
+}
@@ -1,11 +1,40 @@
+use log::{debug, error, info};
 use macroquad::prelude::*;
 use macroquad::time::get_frame_time;
 use macroquad::ui::{hash, root_ui, widgets};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::{cell::RefCell, rc::Rc};
 
-use crate::entity::{Entity, Meteor, MeteorSize, Player};
-use crate::{assets_manager::AssetManager, world::World};
+use crate::entity::{
+    self, Entity, EntitySave, Meteor, MeteorSize, Player, PowerUp, ShipConfig, Shoot, ShootType,
+};
+use crate::save::{self, PlayerSlotSave, SaveFile, WaveSave};
+use crate::{
+    achievements::{Achievement, Achievements, Toasts},
+    arena::Arena,
+    assets_manager::AssetManager,
+    collision,
+    daily::Date,
+    debug::{self, Command, Console, Spawn},
+    event::Event,
+    extra_life::ExtraLifeConfig,
+    game_mode::{GameMode, HighScores},
+    hud::{Hud, Panel, PlayerPanel},
+    input::{Bindings, ShipInput},
+    layer::Layer,
+    net::{self, Lockstep},
+    post::{Canvas, PostProcess, Shake},
+    power_up::{PowerUpConfig, PowerUpKind, PowerUps},
+    settings::{RenderStyle, Settings},
+    spawn::{SpawnConfig, Warning},
+    starfield::{Background, Starfield},
+    stats::Stats,
+    telemetry::Telemetry,
+    vector::Phosphor,
+    weapon::{FireMode, Weapon},
+    world::World,
+};
 
 #[derive(PartialEq, Eq, Hash, Debug)]
 pub enum Type {
@@ -13,28 +42,49 @@ pub enum Type {
     Game,
     Lose,
     Win,
+    Stats,
+    Achievements,
+    Settings,
 }
 
 pub trait State {
     fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool>;
-    fn draw(&self);
+    fn draw(&self, canvas: &Canvas);
+
+    /// Called before the game closes.
+    fn on_quit(&mut self) {}
 }
 
 pub struct StateManager {
     states: HashMap<Type, Box<dyn State>>,
     type_: Type,
+    assets_manager: Rc<RefCell<AssetManager>>,
+    settings: Rc<RefCell<Settings>>,
+    post: PostProcess,
 }
 
 impl StateManager {
     pub async fn new(assets_manager: Rc<RefCell<AssetManager>>) -> Self {
+        let settings = Rc::new(RefCell::new(Settings::load()));
         let mut manager = Self {
             states: HashMap::new(),
             type_: Type::MainMenu,
+            assets_manager: assets_manager.clone(),
+            settings: settings.clone(),
+            post: PostProcess::new(),
         };
 
         manager.states.insert(
             Type::Game,
-            Box::new(GameState::new(assets_manager.clone()).await),
+            Box::new(
+                GameState::new(
+                    assets_manager.clone(),
+                    settings,
+                    PlayerMode::Single,
+                    GameMode::Classic,
+                )
+                .await,
+            ),
         );
         let font = assets_manager
             .borrow_mut()
@@ -43,7 +93,7 @@ impl StateManager {
             .unwrap();
         manager
             .states
-            .insert(Type::Lose, Box::new(LoseState::new(font).await));
+            .insert(Type::Lose, Box::new(LoseState::new(font, None).await));
         manager
             .states
             .insert(Type::MainMenu, Box::new(MainState::new()));
@@ -77,14 +127,130 @@ impl StateManager {
         self.type_ = type_;
     }
 
-    pub fn draw(&self) {
+    /// Starts a new game for `mode` with the rules of `game_mode`, dropping the
+    /// one in progress.
+    pub fn start_game(&mut self, mode: PlayerMode, game_mode: GameMode) {
+        let state = pollster::block_on(GameState::new(
+            self.assets_manager.clone(),
+            self.settings.clone(),
+            mode,
+            game_mode,
+        ));
+        self.states.insert(Type::Game, Box::new(state));
+        self.switch_to(Type::Game);
+    }
+
+    /// Resumes the game of the save file, returns false if there is none. A
+    /// save which does not hold together starts a new game of its mode
+    /// instead.
+    pub fn resume_game(&mut self) -> bool {
+        let Some(save) = save::read() else {
+            return false;
+        };
+
+        let (mode, game_mode) = (save.mode, save.game_mode);
+        let Some(state) = pollster::block_on(GameState::restore(
+            self.assets_manager.clone(),
+            self.settings.clone(),
+            save,
+        )) else {
+            save::remove();
+            self.start_game(mode, game_mode);
+            return true;
+        };
+        self.states.insert(Type::Game, Box::new(state));
+        self.switch_to(Type::Game);
+
+        true
+    }
+
+    /// Shows the summary of the game which just ended.
+    fn show_summary(&mut self, summary: Summary) {
+        let font = pollster::block_on(
+            self.assets_manager
+                .borrow_mut()
+                .get_font("res/trs-million.ttf"),
+        )
+        .unwrap();
+        let state = pollster::block_on(LoseState::new(font, Some(summary)));
+        self.states.insert(Type::Lose, Box::new(state));
+        self.switch_to(Type::Lose);
+    }
+
+    /// Shows the stats of every game played so far.
+    pub fn show_stats(&mut self) {
+        let font = pollster::block_on(
+            self.assets_manager
+                .borrow_mut()
+                .get_font("res/trs-million.ttf"),
+        )
+        .unwrap();
+        let state = StatsState::new(font, Stats::load(), HighScores::load());
+        self.states.insert(Type::Stats, Box::new(state));
+        self.switch_to(Type::Stats);
+    }
+
+    /// Shows the achievements unlocked so far.
+    pub fn show_achievements(&mut self) {
+        let font = pollster::block_on(
+            self.assets_manager
+                .borrow_mut()
+                .get_font("res/trs-million.ttf"),
+        )
+        .unwrap();
+        let state = AchievementsState::new(font, Achievements::load());
+        self.states.insert(Type::Achievements, Box::new(state));
+        self.switch_to(Type::Achievements);
+    }
+
+    pub fn show_settings(&mut self) {
+        let font = pollster::block_on(
+            self.assets_manager
+                .borrow_mut()
+                .get_font("res/trs-million.ttf"),
+        )
+        .unwrap();
+        let state = SettingsState::new(font, self.settings.clone());
+        self.states.insert(Type::Settings, Box::new(state));
+        self.switch_to(Type::Settings);
+    }
+
+    /// Lets every state save what must survive the game being closed.
+    pub fn quit(&mut self) {
+        for state in self.states.values_mut() {
+            state.on_quit();
+        }
+    }
+
+    /// Starts a game against or alongside the peer of `session`, once it is
+    /// connected.
+    pub fn start_online(&mut self, session: Lockstep) {
+        let mut state = pollster::block_on(GameState::new(
+            self.assets_manager.clone(),
+            self.settings.clone(),
+            PlayerMode::Online,
+            GameMode::Classic,
+        ));
+        state.online = Some(Online {
+            session,
+            pending: ShipInput::default(),
+            started: false,
+        });
+        self.states.insert(Type::Game, Box::new(state));
+        self.switch_to(Type::Game);
+    }
+
+    pub fn draw(&mut self) {
         if self.states.is_empty() {
             return;
         }
 
+        let effects = self.settings.borrow().effects;
+        let canvas = self.post.begin(&effects);
         if let Some(state) = self.states.get(&self.type_) {
-            state.draw();
+            state.draw(&canvas);
         }
+        self.post.end(&canvas, &effects);
     }
 }
 
@@ -109,231 +275,1725 @@ impl Level {
     }
 }
 
+/// How many people play and how they share the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerMode {
+    Single,
+    /// Two players taking turns, each one with their own wave.
+    Alternating,
+    /// Two ships on screen at once.
+    Coop {
+        friendly_fire: bool,
+    },
+    /// Two ships on screen at once, each one driven by a peer over the
+    /// network.
+    Online,
+}
+
+impl PlayerMode {
+    fn players(&self) -> usize {
+        match self {
+            PlayerMode::Single => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// What belongs to each player of a game.
+struct PlayerState {
+    lives: Rc<RefCell<u8>>,
+    power_ups: Rc<RefCell<PowerUps>>,
+    input: Rc<RefCell<ShipInput>>,
+    bindings: Bindings,
+    score: u32,
+    /// Time left highlighting the ship just awarded in the lives row.
+    life_flash: f32,
+    /// Number of the wave the player is on.
+    waves: u32,
+    /// Wave put aside while the other player takes their turn.
+    wave: Option<(World, Level)>,
+}
+
+impl PlayerState {
+    fn new(bindings: Bindings, lives: u8) -> Self {
+        Self {
+            lives: Rc::new(RefCell::new(lives)),
+            power_ups: Rc::new(RefCell::new(PowerUps::default())),
+            input: Rc::new(RefCell::new(ShipInput::default())),
+            bindings,
+            score: 0,
+            life_flash: 0.0,
+            waves: 0,
+            wave: None,
+        }
+    }
+
+    fn lives(&self) -> u8 {
+        *self.lives.borrow()
+    }
+}
+
+/// Final scores and stats of a game, shown once it is over.
+struct Summary {
+    /// How the game ended, shown as the title.
+    title: &'static str,
+    scores: Vec<u32>,
+    stats: Stats,
+    game_mode: GameMode,
+    date: Option<Date>,
+    /// High scores of the game mode, this game included.
+    high_scores: Vec<u32>,
+    /// A score of this game entered the high scores.
+    new_high_score: bool,
+}
+
+/// Lockstep session of an online game.
+struct Online {
+    session: Lockstep,
+    /// Local input sampled since it was last sent.
+    pending: ShipInput,
+    /// The game has been reset with the seed of the session.
+    started: bool,
+}
+
 struct GameState {
     assets_manager: Rc<RefCell<AssetManager>>,
     world: World,
     level: Level,
-    lives: Rc<RefCell<u8>>,
-    score: u32,
+    mode: PlayerMode,
+    game_mode: GameMode,
+    /// Date of the daily challenge, whose seed lays out the meteors.
+    date: Option<Date>,
+    /// Random numbers of a daily challenge, started over from its seed on
+    /// every wave and apart from the global generator, which is reseeded from
+    /// it before each frame.
+    daily_rng: Option<rand::RandGenerator>,
+    /// Seconds played since the game started.
+    elapsed: f32,
+    /// Seconds since a meteor was last spawned mid-wave.
+    spawn_timer: f32,
+    /// Meteors about to spawn mid-wave.
+    warnings: Vec<(Warning, Meteor)>,
+    players: Vec<PlayerState>,
+    /// Player whose turn it is in alternating mode.
+    active: usize,
+    ship: ShipConfig,
+    power_up_config: PowerUpConfig,
+    extra_life: ExtraLifeConfig,
+    spawn: SpawnConfig,
+    settings: Rc<RefCell<Settings>>,
+    online: Option<Online>,
+    /// A game has been played since the last reset, and is worth saving.
+    in_progress: bool,
+    console: Console,
+    overlay: bool,
+    /// Ships don't lose lives.
+    god: bool,
+    time_scale: f32,
+    telemetry: Telemetry,
+    stats: Stats,
+    /// Summary of the game which just ended, until it is shown.
+    summary: Option<Summary>,
+    achievements: Achievements,
+    toasts: Toasts,
+    shake: Shake,
+    starfield: Starfield,
+    phosphor: Phosphor,
+    hud: Hud,
+    /// Best score of the high scores when the game started.
+    high_score: u32,
 }
 
 impl GameState {
-    pub async fn new(assets_manager: Rc<RefCell<AssetManager>>) -> Self {
+    pub async fn new(
+        assets_manager: Rc<RefCell<AssetManager>>,
+        settings: Rc<RefCell<Settings>>,
+        mode: PlayerMode,
+        game_mode: GameMode,
+    ) -> Self {
+        let date = (game_mode == GameMode::Daily).then(Date::today);
+        let hud = Hud::new(&assets_manager).await;
+        let weapon = Weapon {
+            fire_mode: settings.borrow().fire_mode,
+            ..Default::default()
+        };
+
         let mut state = Self {
             assets_manager,
             world: World::new(),
             level: Level::One,
-            lives: Rc::new(RefCell::new(3)),
-            score: 0,
+            mode,
+            game_mode,
+            date,
+            daily_rng: None,
+            elapsed: 0.0,
+            spawn_timer: 0.0,
+            warnings: vec![],
+            players: vec![],
+            active: 0,
+            ship: ShipConfig {
+                friendly_fire: mode
+                    == PlayerMode::Coop {
+                        friendly_fire: true,
+                    },
+                weapon,
+                hyperspace: game_mode.hyperspace(),
+                ..Default::default()
+            },
+            power_up_config: PowerUpConfig::default(),
+            extra_life: ExtraLifeConfig::default(),
+            spawn: SpawnConfig::default(),
+            settings,
+            online: None,
+            in_progress: false,
+            console: Console::default(),
+            overlay: false,
+            god: false,
+            time_scale: 1.0,
+            telemetry: Telemetry::from_env(),
+            stats: Stats::default(),
+            summary: None,
+            achievements: Achievements::load(),
+            toasts: Toasts::default(),
+            shake: Shake::default(),
+            starfield: Starfield::new(Date::today().seed()),
+            phosphor: Phosphor::new(),
+            hud,
+            high_score: 0,
         };
 
-        state
-            .world
-            .add(Player::new(state.assets_manager.clone(), state.lives.clone()).await);
-
-        state.init_level().await;
+        state.reset().await;
 
         state
     }
 
-    async fn init_level(&mut self) {
-        let nb_meteors = match self.level {
-            Level::One => 4,
-            Level::Two => 5,
-            Level::Three => 7,
-            Level::Four => 9,
-            Level::Five => 11,
-        };
+    /// Resumes the game of a save file, None if its players do not match its
+    /// mode.
+    async fn restore(
+        assets_manager: Rc<RefCell<AssetManager>>,
+        settings: Rc<RefCell<Settings>>,
+        save: SaveFile,
+    ) -> Option<Self> {
+        let mut state = Self::new(assets_manager, settings, save.mode, save.game_mode).await;
+        if save.players.len() != state.players.len() || save.active >= state.players.len() {
+            error!(
+                "save rejected mode={:?} players={} active={}",
+                save.mode,
+                save.players.len(),
+                save.active
+            );
+            return None;
+        }
 
-        for _ in 0..nb_meteors {
-            let mut meteor = Meteor::new(self.assets_manager.clone(), MeteorSize::Big).await;
-            meteor.set_position(Vec2 {
-                x: rand::gen_range(0.0, screen_width()),
-                y: rand::gen_range(0.0, screen_height()),
-            });
-            self.world.add(meteor);
+        state.elapsed = save.elapsed;
+        state.date = save.date;
+        state.active = save.active;
+        state.in_progress = true;
+        state.stats = save.stats;
+        state.high_score = state.best_score();
+        state.hud.clear_messages();
+        state.hud.message("GET READY");
+
+        for (player, slot) in state.players.iter_mut().zip(save.players.iter()) {
+            *player.lives.borrow_mut() = slot.lives;
+            player.score = slot.score;
+            player.waves = slot.waves.max(1);
+
+            let mut power_ups = player.power_ups.borrow_mut();
+            for (kind, remaining) in slot.power_ups.iter() {
+                power_ups.grant(*kind, *remaining);
+            }
+        }
+
+        for (index, slot) in save.players.into_iter().enumerate() {
+            if let Some(wave) = slot.wave {
+                let level = Level::from_u32(wave.level);
+                let world = state.restore_wave(wave).await;
+                if let Some(player) = state.players.get_mut(index) {
+                    player.wave = Some((world, level));
+                }
+            }
         }
+
+        state.level = Level::from_u32(save.wave.level);
+        state.world = state.restore_wave(save.wave).await;
+        state.start_daily_wave();
+
+        Some(state)
     }
 
-    async fn reset(&mut self) {
-        self.level = Level::One;
-        self.score = 0;
-        *self.lives.borrow_mut() = 3;
-        self.world = World::new();
-        self.world
-            .add(Player::new(self.assets_manager.clone(), self.lives.clone()).await);
+    /// Toggles the overlay on F3 and runs the commands of the console.
+    fn debug_console(&mut self) {
+        if is_key_pressed(KeyCode::F3) {
+            self.overlay = !self.overlay;
+        }
 
-        self.init_level().await;
+        if let Some(command) = self.console.update() {
+            self.run_command(command);
+        }
     }
-}
 
-impl State for GameState {
-    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
-        self.score += self.world.update(get_frame_time());
+    fn run_command(&mut self, command: Command) {
+        let assets = self.assets_manager.clone();
 
-        if *self.lives.borrow() == 0 {
-            pollster::block_on(self.reset());
-            return Box::new(|world| {
-                world.switch_to(Type::Lose);
-                true
-            });
+        match command {
+            Command::Spawn(Spawn::Meteor(size)) => {
+                let mut meteor = pollster::block_on(Meteor::new(assets, size));
+                let size = meteor.get_data().sprite.size();
+                meteor.set_position(self.world.find_clear_position(size, 100.0));
+                self.world.add(meteor);
+            }
+            Command::Spawn(Spawn::PowerUp(kind)) => {
+                let position = self.world.find_clear_position(Vec2::ZERO, 100.0);
+                self.world.add(PowerUp::new(
+                    assets,
+                    kind,
+                    position,
+                    self.power_up_config.lifetime,
+                ));
+            }
+            Command::God => {
+                self.god = !self.god;
+                let state = if self.god { "on" } else { "off" };
+                self.console.print(format!("god mode {}", state));
+            }
+            Command::Level(level) => {
+                self.world
+                    .remove_where(|type_| matches!(type_, entity::Type::Meteor(_)));
+                self.level = Level::from_u32(level);
+                pollster::block_on(self.init_level());
+            }
+            Command::TimeScale(scale) => self.time_scale = scale,
+            Command::Overlay => self.overlay = !self.overlay,
         }
+    }
 
-        if self.world.size() == 1 {
-            self.level = Level::from_u32((self.level.clone() as u32) + 1);
-            pollster::block_on(self.init_level());
+    /// Dumps the world to JSON on F5, and rebuilds it from the dump on F9.
+    fn debug_dump(&mut self) {
+        const DUMP: &str = "world.json";
+
+        if is_key_pressed(KeyCode::F5) {
+            let dump = self
+                .world
+                .to_json()
+                .map_err(|error| error.to_string())
+                .and_then(|json| std::fs::write(DUMP, json).map_err(|error| error.to_string()));
+
+            match dump {
+                Ok(()) => info!("world dumped path={}", DUMP),
+                Err(error) => error!("world not dumped path={} error={}", DUMP, error),
+            }
         }
 
-        Box::new(|_| true)
+        if is_key_pressed(KeyCode::F9) {
+            let snapshot = std::fs::read_to_string(DUMP)
+                .map_err(|error| error.to_string())
+                .and_then(|json| {
+                    World::snapshot_from_json(&json).map_err(|error| error.to_string())
+                });
+
+            match snapshot {
+                Ok(snapshot) => {
+                    let wave = WaveSave {
+                        level: self.level.clone() as u32,
+                        entities: snapshot
+                            .into_iter()
+                            .filter_map(|entity| entity.state)
+                            .collect(),
+                    };
+                    self.world = pollster::block_on(self.restore_wave(wave));
+                }
+                Err(error) => error!("world not loaded path={} error={}", DUMP, error),
+            }
+        }
     }
 
-    fn draw(&self) {
-        // Draw score
-        let font = pollster::block_on(
-            self.assets_manager
-                .borrow_mut()
-                .get_font("res/trs-million.ttf"),
-        )
-        .unwrap();
-        draw_text_ex(
-            &format!("Score: {}", self.score),
-            0.0,
-            30.0,
-            TextParams {
-                font: *font.borrow(),
-                font_size: 30,
-                color: WHITE,
-                ..Default::default()
+    /// Current game, to write in a save file.
+    fn save(&self) -> SaveFile {
+        SaveFile {
+            version: save::VERSION,
+            mode: self.mode,
+            game_mode: self.game_mode,
+            date: self.date,
+            elapsed: self.elapsed,
+            active: self.active,
+            players: self
+                .players
+                .iter()
+                .map(|player| PlayerSlotSave {
+                    lives: player.lives(),
+                    score: player.score,
+                    waves: player.waves,
+                    power_ups: player.power_ups.borrow().active(),
+                    wave: player.wave.as_ref().map(|(world, level)| WaveSave {
+                        level: level.clone() as u32,
+                        entities: world.save(),
+                    }),
+                })
+                .collect(),
+            wave: WaveSave {
+                level: self.level.clone() as u32,
+                entities: self.world.save(),
             },
-        );
+            stats: self.stats.clone(),
+        }
+    }
 
-        // Draw lives
-        let texture = pollster::block_on(
-            self.assets_manager
-                .borrow_mut()
-                .get_texture("res/Player/life.png"),
-        )
-        .unwrap();
-        let width = screen_width();
-        for i in 0..*self.lives.borrow() {
-            draw_texture(
-                *texture.borrow(),
-                width - (*self.lives.borrow() - i) as f32 * texture.borrow().width(),
-                0.0,
-                WHITE,
-            );
+    /// Rebuilds the world of a saved wave, ships being linked back to their
+    /// player.
+    async fn restore_wave(&self, wave: WaveSave) -> World {
+        let mut world = self.new_world();
+        world.set_drop_table(self.power_up_config.drop_table(wave.level));
+
+        // Ships first, their shots count against them
+        let mut shots = HashMap::new();
+        let (ships, entities): (Vec<_>, Vec<_>) = wave
+            .entities
+            .into_iter()
+            .partition(|entity| matches!(entity, EntitySave::Player(_)));
+
+        for entity in ships {
+            let EntitySave::Player(ship) = entity else {
+                continue;
+            };
+            let Some(player) = self.players.get(ship.slot as usize) else {
+                continue;
+            };
+
+            let ship = Player::restore(
+                self.assets_manager.clone(),
+                player.input.clone(),
+                player.lives.clone(),
+                player.power_ups.clone(),
+                &self.ship,
+                ship,
+            )
+            .await;
+            shots.insert(ship.get_type(), ship.shots());
+            world.add(ship);
+        }
+
+        for entity in entities {
+            let assets = self.assets_manager.clone();
+            match entity {
+                EntitySave::Player(_) => (),
+                EntitySave::Shoot(shoot) => {
+                    let counter = match shoot.type_ {
+                        ShootType::Player(slot) => shots.get(&entity::Type::Player(slot)).cloned(),
+                        ShootType::Enemy => None,
+                    };
+                    let counter = counter.unwrap_or_default();
+                    world.add(Shoot::restore(assets, counter, shoot).await);
+                }
+                EntitySave::Meteor(meteor) => world.add(Meteor::restore(assets, meteor).await),
+                EntitySave::PowerUp(power_up) => {
+                    world.add(PowerUp::restore(assets, power_up).await)
+                }
+            }
         }
 
-        self.world.draw()
+        world
     }
-}
 
-struct MainState {}
+    fn new_world(&self) -> World {
+        let mut world = World::new();
+        world.set_bounds(self.ship.arena);
 
-impl MainState {
-    pub fn new() -> Self {
-        Self {}
+        world
     }
-}
-
-impl State for MainState {
-    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
-        let mut play = false;
-        let mut quit = false;
 
-        let width = screen_width();
-        let height = screen_height();
+    /// Players whose ship is in the current world.
+    fn playing(&self) -> Vec<usize> {
+        match self.mode {
+            PlayerMode::Alternating => vec![self.active],
+            _ => (0..self.players.len()).collect(),
+        }
+    }
 
-        widgets::Window::new(
-            hash!(),
-            vec2((width - 120.) * 0.5, (height - 70.) * 0.5),
-            vec2(120., 148.),
+    async fn spawn_player(&mut self, slot: usize) {
+        let player = &self.players[slot];
+        let mut ship = Player::new(
+            self.assets_manager.clone(),
+            slot as u8,
+            player.input.clone(),
+            player.lives.clone(),
+            player.power_ups.clone(),
+            &self.ship,
         )
-        .movable(false)
-        .titlebar(false)
-        .ui(&mut *root_ui(), |ui| {
-            if widgets::Button::new("Play").size(vec2(113., 70.)).ui(ui) {
-                play = true;
-            }
+        .await;
 
-            if widgets::Button::new("Quit").size(vec2(113., 70.)).ui(ui) {
-                quit = true;
-            }
-        });
+        ship.set_position(self.home(slot));
 
-        if play {
-            return Box::new(|state_manager| {
-                state_manager.switch_to(Type::Game);
+        self.world.add(ship);
+    }
 
-                true
-            });
+    /// Where the ship of `slot` appears.
+    fn home(&self, slot: usize) -> Vec2 {
+        let arena = self.world.bounds();
+        match self.mode {
+            // Side by side
+            PlayerMode::Coop { .. } | PlayerMode::Online => Vec2 {
+                x: arena.x / 2.0 + (slot as f32 - 0.5) * 100.0,
+                y: arena.y / 2.0,
+            },
+            _ => arena / 2.0,
         }
-
-        Box::new(move |_| !quit)
     }
 
-    fn draw(&self) {}
-}
+    async fn init_level(&mut self) {
+        let nb_meteors = match self.level {
+            Level::One => 4,
+            Level::Two => 5,
+            Level::Three => 7,
+            Level::Four => 9,
+            Level::Five => 11,
+        };
 
-struct LoseState {
-    font: Rc<RefCell<Font>>,
-}
+        let level = self.level.clone() as u32;
+        self.world
+            .set_drop_table(self.power_up_config.drop_table(level));
+        if let Some(player) = self.players.get_mut(self.active) {
+            player.waves += 1;
+            self.hud.message(format!("WAVE {}", player.waves));
+        }
+        self.telemetry.start_wave(level);
+        self.achievements.start_wave();
+        info!("level started level={} meteors={}", level, nb_meteors);
 
-impl LoseState {
-    pub async fn new(font: Rc<RefCell<Font>>) -> Self {
-        Self { font }
+        self.start_daily_wave();
+        self.reseed_daily();
+
+        // Every daily wave starts with the ships back where they appear, so
+        // that the layout only depends on the seed
+        let homes: Vec<Vec2> = (0..self.players.len())
+            .map(|slot| self.home(slot))
+            .collect();
+        if self.date.is_some() {
+            for entity in self.world.entities.iter_mut() {
+                if let entity::Type::Player(slot) = entity.get_type() {
+                    entity.set_position(homes[slot as usize]);
+                }
+            }
+        }
+        let ships: Vec<Vec2> = self.playing().into_iter().map(|slot| homes[slot]).collect();
+
+        for _ in 0..nb_meteors {
+            let mut meteor = self.new_meteor().await;
+            let (_, radius) = collision::collider(meteor.get_data());
+            let position = match self.date {
+                Some(_) => self.spawn.position(self.world.bounds(), &ships, radius),
+                None => self.spawn_position(radius),
+            };
+            meteor.set_position(position);
+            self.world.add(meteor);
+        }
     }
-}
 
-impl State for LoseState {
-    fn draw(&self) {
-        let text = "You lose the game!";
-        let dimensions = measure_text(text, Some(*self.font.borrow()), 50, 1.0);
+    /// Starts the random numbers of a daily challenge over from the seed of
+    /// the wave being played, so every wave is laid out the same for everyone.
+    fn start_daily_wave(&mut self) {
+        let Some(date) = self.date else {
+            return;
+        };
+        let wave = self
+            .players
+            .get(self.active)
+            .map(|player| player.waves)
+            .unwrap_or_default();
 
-        draw_text_ex(
-            text,
-            (screen_width() - dimensions.width) * 0.5,
-            (screen_height() - dimensions.height) * 0.5,
-            TextParams {
-                font: *self.font.borrow(),
-                font_size: 50,
-                color: WHITE,
-                ..Default::default()
-            },
-        );
+        let rng = rand::RandGenerator::new();
+        rng.srand(date.seed().wrapping_add(wave as u64));
+        self.daily_rng = Some(rng);
     }
 
-    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
-        let mut play = false;
-        let mut quit = false;
-        let height = screen_height() * 0.5;
-        let width = screen_width();
+    /// Reseeds the global generator from the daily one, so that splits, drops
+    /// and spawns only depend on the daily challenge.
+    fn reseed_daily(&self) {
+        if let Some(rng) = self.daily_rng.as_ref() {
+            rand::srand(u64::from(rng.rand()) << 32 | u64::from(rng.rand()));
+        }
+    }
 
-        widgets::Window::new(
-            hash!(),
-            vec2((width - 120.) * 0.5, height + 80.0),
-            vec2(120., 148.),
-        )
-        .movable(false)
-        .titlebar(false)
-        .ui(&mut root_ui(), |ui| {
-            if widgets::Button::new("Retry").size(vec2(113., 70.)).ui(ui) {
-                play = true;
-            }
+    /// Position for a new meteor of `radius`, away from every ship.
+    fn spawn_position(&self, radius: f32) -> Vec2 {
+        let ships = self
+            .world
+            .positions_of(|type_| matches!(type_, entity::Type::Player(_)));
 
-            if widgets::Button::new("Quit").size(vec2(113., 70.)).ui(ui) {
-                quit = true;
-            }
-        });
+        self.spawn.position(self.world.bounds(), &ships, radius)
+    }
 
-        if play {
-            return Box::new(|state_manager| {
-                state_manager.switch_to(Type::Game);
+    /// Draws the scores, lives and messages over the game, in screen pixels.
+    fn draw_hud(&self, arena: &Arena) {
+        let playing = self.playing();
+        let players = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(slot, player)| PlayerPanel {
+                label: (self.players.len() > 1).then(|| format!("P{}", slot + 1)),
+                score: player.score,
+                lives: player.lives(),
+                // Blink the newest ship after it has been awarded
+                hide_newest: player.life_flash > 0.0 && (player.life_flash * 8.0) as i32 % 2 == 0,
+                // Dim the player waiting for their turn
+                color: if playing.contains(&slot) { WHITE } else { GRAY },
+                power_ups: if playing.contains(&slot) {
+                    player.power_ups.borrow().active()
+                } else {
+                    vec![]
+                },
+            })
+            .collect();
 
-                true
+        let best = self.players.iter().map(|player| player.score).max();
+        let status = self
+            .online
+            .as_ref()
+            .and_then(|online| match online.session.desync() {
+                _ if !online.started => Some("Waiting for peer...".to_string()),
+                Some(frame) => Some(format!("Desync at frame {}", frame)),
+                None => None,
             });
+
+        let panel = Panel {
+            players,
+            high_score: self.high_score.max(best.unwrap_or_default()),
+            wave: self
+                .players
+                .get(self.active)
+                .map_or(1, |player| player.waves),
+            time_left: self
+                .game_mode
+                .time_limit()
+                .map(|limit| (limit - self.elapsed).max(0.0).ceil() as u32),
+            status,
+        };
+        self.hud.draw(arena, &panel);
+
+        self.toasts.draw(self.hud.font());
+        if self.overlay {
+            debug::draw_stats(&self.world);
+        }
+        self.console.draw();
+    }
+
+    /// Mean velocity of the ships in play, which the sky drifts against.
+    fn ships_velocity(&self) -> Vec2 {
+        let velocities: Vec<Vec2> = self
+            .world
+            .entities
+            .iter()
+            .filter(|entity| matches!(entity.get_type(), entity::Type::Player(_)))
+            .map(|entity| entity.velocity())
+            .collect();
+
+        if velocities.is_empty() {
+            return Vec2::ZERO;
         }
 
-        Box::new(move |_| !quit)
+        velocities.iter().sum::<Vec2>() / velocities.len() as f32
+    }
+
+    /// Big meteor, placed by the caller.
+    async fn new_meteor(&self) -> Meteor {
+        Meteor::new(self.assets_manager.clone(), MeteorSize::Big).await
+    }
+
+    async fn reset(&mut self) {
+        self.stats = Stats::default();
+        self.elapsed = 0.0;
+        self.spawn_timer = 0.0;
+        self.warnings.clear();
+        self.level = Level::One;
+        self.active = 0;
+        let lives = self.game_mode.lives();
+        self.players = (0..self.mode.players())
+            .map(|slot| match (self.mode, slot) {
+                (PlayerMode::Coop { .. }, 1) => PlayerState::new(Bindings::WASD, lives),
+                _ => PlayerState::new(Bindings::ARROWS, lives),
+            })
+            .collect();
+        self.world = self.new_world();
+        self.high_score = self.best_score();
+        self.hud.clear_messages();
+        self.hud.message("GET READY");
+
+        for slot in self.playing() {
+            self.spawn_player(slot).await;
+        }
+
+        self.init_level().await;
+    }
+
+    /// Hands the game over to the other player in alternating mode, putting
+    /// the current wave aside until their turn comes back.
+    async fn switch_turn(&mut self) {
+        let next = (self.active + 1) % self.players.len();
+        if next == self.active || self.players[next].lives() == 0 {
+            return;
+        }
+
+        self.telemetry.finish_wave("turn over");
+        debug!("turn switched player={}", next + 1);
+
+        let world = self.new_world();
+        let world = std::mem::replace(&mut self.world, world);
+        if self.players[self.active].lives() > 0 {
+            self.players[self.active].wave = Some((world, self.level.clone()));
+        }
+
+        self.active = next;
+        self.hud.message(format!("PLAYER {}", next + 1));
+        match self.players[next].wave.take() {
+            Some((world, level)) => {
+                self.world = world;
+                self.level = level;
+                self.telemetry.start_wave(self.level.clone() as u32);
+                self.hud.message("GET READY");
+                self.start_daily_wave();
+            }
+            None => {
+                self.level = Level::One;
+                self.spawn_player(next).await;
+                self.init_level().await;
+            }
+        }
+    }
+
+    fn add_score(&mut self, slot: usize, points: u32) {
+        let Some(player) = self.players.get_mut(slot) else {
+            return;
+        };
+
+        let score = player.score;
+        player.score += points;
+
+        for _ in 0..self.extra_life.earned(score, player.score) {
+            self.award_life(slot);
+        }
+    }
+
+    fn award_life(&mut self, slot: usize) {
+        let Some(player) = self.players.get_mut(slot) else {
+            return;
+        };
+
+        let mut lives = player.lives.borrow_mut();
+        if *lives >= self.extra_life.max_lives {
+            return;
+        }
+
+        *lives += 1;
+        player.life_flash = 2.0;
+        drop(lives);
+        self.hud.message("EXTRA SHIP");
+
+        pollster::block_on(
+            self.assets_manager
+                .borrow_mut()
+                .play_sound("res/sounds/spawn1.ogg", 1.0),
+        );
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::MeteorDestroyed { size, .. } => self.shake.add(match size {
+                MeteorSize::Big => 0.4,
+                MeteorSize::Medium => 0.25,
+                MeteorSize::Small => 0.1,
+            }),
+            Event::ShipDestroyed { .. } => self.shake.add(0.8),
+            _ => (),
+        }
+
+        match event {
+            Event::MeteorDestroyed {
+                size,
+                position,
+                by: Some(player),
+                ..
+            } => {
+                self.hud.popup(size.points(), position);
+                self.add_score(player as usize, size.points());
+            }
+            Event::MeteorDestroyed { .. }
+            | Event::ShotFired { .. }
+            | Event::HyperspaceJump { .. } => (),
+            Event::ShipDestroyed { player, cause } => {
+                debug!("ship destroyed player={} cause={:?}", player + 1, cause)
+            }
+            Event::PowerUpCollected {
+                kind: PowerUpKind::ExtraLife,
+                player,
+            } => self.award_life(player as usize),
+            Event::PowerUpCollected {
+                kind: PowerUpKind::SmartBomb,
+                player,
+            } => {
+                // As if the player had rammed each of them, so they explode,
+                // split and drop like any other
+                self.world.destroy_where(
+                    |type_| matches!(type_, entity::Type::Meteor(_) | entity::Type::Enemy),
+                    &entity::Type::Player(player),
+                );
+            }
+            Event::PowerUpCollected { kind, player } => {
+                if let Some(player) = self.players.get(player as usize) {
+                    player
+                        .power_ups
+                        .borrow_mut()
+                        .grant(kind, self.power_up_config.duration);
+                }
+            }
+        }
+    }
+
+    /// Exchanges inputs with the peer and advances the world by one lockstep
+    /// frame once both are known.
+    fn update_online(&mut self) -> Option<Type> {
+        let online = self.online.as_mut()?;
+        online.session.poll();
+
+        if !online.started {
+            let seed = online.session.seed()?;
+            if !online.session.is_connected() {
+                return None;
+            }
+
+            // Both peers start from the same seed and the same arena
+            online.started = true;
+            self.ship.arena = online.session.arena;
+            self.ship.friendly_fire = online.session.friendly_fire;
+            // Both peers simulate both ships, which must fire the same way
+            self.ship.weapon.fire_mode = FireMode::default();
+            rand::srand(seed);
+            pollster::block_on(self.reset());
+
+            return None;
+        }
+
+        let online = self.online.as_mut()?;
+        online.pending.merge(Bindings::ARROWS.sample());
+        if online.session.push_local(online.pending) {
+            online.pending = ShipInput::default();
+        }
+
+        let inputs = online.session.inputs()?;
+        let (seed, frame) = (online.session.seed()?, online.session.frame());
+        for (player, input) in self.players.iter().zip(inputs) {
+            *player.input.borrow_mut() = input;
+        }
+
+        net::reseed(seed, frame);
+        let next = self.simulate(net::FIXED_DT);
+
+        let checksum = self.world.checksum();
+        if let Some(online) = self.online.as_mut() {
+            online.session.advance(checksum);
+        }
+
+        next
+    }
+
+    /// Shows and saves the achievements unlocked since the last call.
+    fn notify_achievements(&mut self) {
+        let unlocked = self.achievements.take_new();
+        if unlocked.is_empty() {
+            return;
+        }
+
+        for achievement in unlocked {
+            info!("achievement unlocked achievement={:?}", achievement);
+            self.toasts.push(achievement);
+        }
+        self.save_achievements();
+    }
+
+    fn save_achievements(&self) {
+        if let Err(error) = self.achievements.save() {
+            error!("achievements not saved error={}", error);
+        }
+    }
+
+    /// Adds the stats of the game which just ended to the lifetime ones, and
+    /// keeps them for the summary.
+    fn finish_game(&mut self, scores: Vec<u32>) {
+        let mut stats = std::mem::take(&mut self.stats);
+        stats.finish(&scores);
+
+        let mut lifetime = Stats::load();
+        lifetime.add(&stats);
+        if let Err(error) = lifetime.save() {
+            error!("stats not saved error={}", error);
+        }
+
+        let mut high_scores = HighScores::load();
+        let mut new_high_score = false;
+        for score in scores.iter() {
+            new_high_score |= match self.date {
+                Some(date) => high_scores.submit_daily(date, *score),
+                None => high_scores.submit(self.game_mode, *score),
+            };
+        }
+        if let Err(error) = high_scores.save() {
+            error!("high scores not saved error={}", error);
+        }
+
+        self.summary = Some(Summary {
+            title: self.ending(),
+            scores,
+            stats,
+            game_mode: self.game_mode,
+            date: self.date,
+            high_scores: match self.date {
+                Some(date) => high_scores.daily_table(date).to_vec(),
+                None => high_scores.table(self.game_mode).to_vec(),
+            },
+            new_high_score,
+        });
+        self.save_achievements();
+    }
+
+    /// The time of a timed game ran out.
+    fn is_time_up(&self) -> bool {
+        self.game_mode
+            .time_limit()
+            .is_some_and(|limit| self.elapsed >= limit)
+    }
+
+    /// Title of the summary, as the game ends now.
+    fn ending(&self) -> &'static str {
+        if self.is_time_up() {
+            "Time up!"
+        } else if !self.game_mode.loses_lives() {
+            "Run ended"
+        } else {
+            "You lose the game!"
+        }
+    }
+
+    /// Best score of the high scores this game is entered in.
+    fn best_score(&self) -> u32 {
+        let high_scores = HighScores::load();
+        let table = match self.date {
+            Some(date) => high_scores.daily_table(date),
+            None => high_scores.table(self.game_mode),
+        };
+
+        table.first().copied().unwrap_or_default()
+    }
+
+    /// Ends the game, keeping its summary, and starts over a new one.
+    fn game_over(&mut self) -> Type {
+        let scores: Vec<u32> = self.players.iter().map(|player| player.score).collect();
+        info!(
+            "game over mode={:?} game_mode={:?} scores={:?}",
+            self.mode, self.game_mode, scores
+        );
+        self.telemetry.finish_wave("game over");
+        self.finish_game(scores);
+        pollster::block_on(self.reset());
+        self.in_progress = false;
+        save::remove();
+
+        Type::Lose
+    }
+
+    /// Advances the game by `dt` with the inputs already set, returning the
+    /// state to switch to, if any.
+    fn simulate(&mut self, dt: f32) -> Option<Type> {
+        self.in_progress = true;
+        self.reseed_daily();
+        let playing = self.playing();
+        let lives: Vec<u8> = self.players.iter().map(PlayerState::lives).collect();
+        self.world.update(dt);
+        self.telemetry.update(dt);
+        // Frozen along with the game while waiting for the other peer
+        self.hud.update(dt);
+        self.shake.update(dt);
+        self.starfield
+            .update(dt, self.ships_velocity(), self.world.bounds());
+        self.stats.time += dt;
+        self.elapsed += dt;
+
+        if playing
+            .iter()
+            .any(|slot| self.players[*slot].input.borrow().thrust)
+        {
+            self.achievements.thrust();
+        }
+
+        if self.god || !self.game_mode.loses_lives() {
+            for (player, lives) in self.players.iter().zip(lives.iter()) {
+                let mut current = player.lives.borrow_mut();
+                *current = (*current).max(*lives);
+            }
+        }
+
+        for slot in playing.iter() {
+            let player = &mut self.players[*slot];
+            player.power_ups.borrow_mut().update(dt);
+            player.life_flash -= dt;
+        }
+
+        for event in self.world.drain_events() {
+            self.telemetry.record(&event);
+            self.stats.record(&event);
+            self.achievements.record(&event);
+            self.handle_event(event);
+        }
+
+        self.notify_achievements();
+
+        if let Some(interval) = self.game_mode.spawn_interval(self.elapsed) {
+            self.spawn_timer += dt;
+            if self.spawn_timer >= interval {
+                self.spawn_timer = 0.0;
+                let mut meteor = pollster::block_on(self.new_meteor());
+                let (_, radius) = collision::collider(meteor.get_data());
+                let position = self.spawn_position(radius);
+                meteor.set_position(position);
+                self.warnings.push((
+                    Warning {
+                        position,
+                        time_left: self.spawn.warning_time,
+                    },
+                    meteor,
+                ));
+            }
+        }
+
+        for (warning, _) in self.warnings.iter_mut() {
+            warning.time_left -= dt;
+        }
+        let (due, warnings): (Vec<_>, Vec<_>) = self
+            .warnings
+            .drain(..)
+            .partition(|(warning, _)| warning.time_left <= 0.0);
+        self.warnings = warnings;
+        // The ships may have come close since the warning showed up
+        let ships = self
+            .world
+            .positions_of(|type_| matches!(type_, entity::Type::Player(_)));
+        for (_, mut meteor) in due {
+            let (position, radius) = collision::collider(meteor.get_data());
+            meteor.set_position(
+                self.spawn
+                    .push_out(position, self.world.bounds(), &ships, radius),
+            );
+            self.world.add(meteor);
+        }
+
+        if self.is_time_up() || self.players.iter().all(|player| player.lives() == 0) {
+            return Some(self.game_over());
+        }
+
+        if self.mode == PlayerMode::Alternating
+            && self.players[self.active].lives() < lives[self.active]
+        {
+            pollster::block_on(self.switch_turn());
+        }
+
+        if self
+            .world
+            .count(|type_| matches!(type_, entity::Type::Meteor(_)))
+            == 0
+        {
+            self.telemetry.finish_wave("cleared");
+            self.stats.waves_cleared += 1;
+            self.achievements
+                .wave_cleared(self.level.clone() as u32, self.stats.hyperspace_jumps);
+            self.level = Level::from_u32((self.level.clone() as u32) + 1);
+            pollster::block_on(self.init_level());
+        }
+
+        None
+    }
+}
+
+impl State for GameState {
+    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
+        let leave = is_key_pressed(KeyCode::Escape);
+
+        // Back to the menu, the game can be resumed from there
+        if leave && (self.game_mode.loses_lives() || !self.in_progress) {
+            self.on_quit();
+            return Box::new(|state_manager| {
+                state_manager.switch_to(Type::MainMenu);
+                true
+            });
+        }
+
+        if cfg!(debug_assertions) && self.online.is_none() {
+            self.debug_dump();
+            self.debug_console();
+        }
+
+        let next = if leave {
+            // Zen games never end on their own, leaving them does
+            Some(self.game_over())
+        } else if self.online.is_some() {
+            self.update_online()
+        } else {
+            for slot in self.playing() {
+                let player = &self.players[slot];
+                // Typing in the console doesn't fly the ship
+                *player.input.borrow_mut() = if self.console.open {
+                    ShipInput::default()
+                } else {
+                    player.bindings.sample()
+                };
+            }
+
+            self.simulate(get_frame_time() * self.time_scale)
+        };
+        self.toasts.update(get_frame_time());
+
+        let summary = self.summary.take();
+        match next {
+            Some(type_) => Box::new(|state_manager| {
+                match summary {
+                    Some(summary) => state_manager.show_summary(summary),
+                    None => state_manager.switch_to(type_),
+                }
+                true
+            }),
+            None => Box::new(|_| true),
+        }
+    }
+
+    fn on_quit(&mut self) {
+        self.save_achievements();
+
+        // Online games can't be resumed without the other peer
+        if !self.in_progress || self.online.is_some() {
+            return;
+        }
+
+        if let Err(error) = save::write(&self.save()) {
+            error!("game not saved error={}", error);
+        }
+    }
+
+    fn draw(&self, canvas: &Canvas) {
+        let started = self.online.as_ref().is_none_or(|online| online.started);
+        let settings = *self.settings.borrow();
+        let arena = Arena {
+            size: self.world.bounds(),
+            aspect: settings.aspect,
+        };
+
+        let mut camera = arena.camera();
+        if settings.effects.shake {
+            camera.target += self.shake.offset();
+        }
+        canvas.set_camera(camera);
+        let camera = Camera2D {
+            render_target: canvas.target,
+            ..camera
+        };
+        let glow = settings.style == RenderStyle::Vector;
+
+        for layer in Layer::ALL {
+            match layer {
+                // Only the HUD until the other peer shows up
+                _ if !started && layer != Layer::Hud => (),
+                Layer::Background => {
+                    if settings.background == Background::Stars {
+                        self.starfield.draw(self.world.bounds());
+                    }
+                    if glow {
+                        self.phosphor.begin(&camera);
+                    }
+                }
+                Layer::Hud => {
+                    if started {
+                        if glow {
+                            self.phosphor.end(&camera);
+                        }
+                        for (warning, _) in self.warnings.iter() {
+                            warning.draw(self.world.bounds());
+                        }
+                        if self.overlay {
+                            debug::draw_overlay(&self.world);
+                        }
+                        arena.draw_bars();
+                    }
+                    canvas.set_screen();
+                    self.draw_hud(&arena);
+                }
+                layer => self.world.draw_layer(layer, settings.style),
+            }
+        }
+    }
+}
+
+struct MainState {
+    friendly_fire: bool,
+    game_mode: GameMode,
+    /// Whether a saved game can be resumed, checked when the menu shows up.
+    resume: Option<bool>,
+}
+
+impl MainState {
+    pub fn new() -> Self {
+        Self {
+            friendly_fire: false,
+            game_mode: GameMode::Classic,
+            resume: None,
+        }
+    }
+}
+
+impl State for MainState {
+    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
+        let mut play = None;
+        let mut quit = false;
+
+        let width = screen_width();
+        let height = screen_height();
+        let friendly_fire = &mut self.friendly_fire;
+        let game_mode = &mut self.game_mode;
+
+        let resume = *self.resume.get_or_insert_with(save::is_resumable);
+        let mut continue_ = false;
+        let mut stats = false;
+        let mut achievements = false;
+        let mut settings = false;
+        let window_height = if resume { 515. } else { 460. };
+
+        widgets::Window::new(
+            hash!(),
+            vec2((width - 160.) * 0.5, (height - window_height) * 0.5),
+            vec2(160., window_height),
+        )
+        .movable(false)
+        .titlebar(false)
+        .ui(&mut *root_ui(), |ui| {
+            if resume
+                && widgets::Button::new("Continue")
+                    .size(vec2(153., 45.))
+                    .ui(ui)
+            {
+                continue_ = true;
+            }
+
+            if widgets::Button::new(game_mode.label())
+                .size(vec2(153., 45.))
+                .ui(ui)
+            {
+                *game_mode = game_mode.next();
+            }
+
+            if widgets::Button::new("1 Player")
+                .size(vec2(153., 45.))
+                .ui(ui)
+            {
+                play = Some(PlayerMode::Single);
+            }
+
+            if widgets::Button::new("2 Players")
+                .size(vec2(153., 45.))
+                .ui(ui)
+            {
+                play = Some(PlayerMode::Alternating);
+            }
+
+            if widgets::Button::new("2 Players Co-op")
+                .size(vec2(153., 45.))
+                .ui(ui)
+            {
+                play = Some(PlayerMode::Coop {
+                    friendly_fire: *friendly_fire,
+                });
+            }
+
+            ui.checkbox(hash!(), "Friendly fire", friendly_fire);
+
+            if widgets::Button::new("Stats").size(vec2(153., 45.)).ui(ui) {
+                stats = true;
+            }
+
+            if widgets::Button::new("Achievements")
+                .size(vec2(153., 45.))
+                .ui(ui)
+            {
+                achievements = true;
+            }
+
+            if widgets::Button::new("Settings")
+                .size(vec2(153., 45.))
+                .ui(ui)
+            {
+                settings = true;
+            }
+
+            if widgets::Button::new("Quit").size(vec2(153., 45.)).ui(ui) {
+                quit = true;
+            }
+        });
+
+        // Leaving the menu, a game may be saved or over by the time it is back
+        if continue_ || stats || achievements || settings || play.is_some() {
+            self.resume = None;
+        }
+
+        if continue_ {
+            return Box::new(|state_manager| {
+                state_manager.resume_game();
+
+                true
+            });
+        }
+
+        if stats {
+            return Box::new(|state_manager| {
+                state_manager.show_stats();
+
+                true
+            });
+        }
+
+        if achievements {
+            return Box::new(|state_manager| {
+                state_manager.show_achievements();
+
+                true
+            });
+        }
+
+        if settings {
+            return Box::new(|state_manager| {
+                state_manager.show_settings();
+
+                true
+            });
+        }
+
+        if let Some(mode) = play {
+            let game_mode = self.game_mode;
+            return Box::new(move |state_manager| {
+                state_manager.start_game(mode, game_mode);
+
+                true
+            });
+        }
+
+        Box::new(move |_| !quit)
+    }
+
+    fn draw(&self, _canvas: &Canvas) {}
+}
+
+/// High scores of a table named `label` on a single line.
+fn high_scores_line(label: &str, scores: &[u32]) -> String {
+    if scores.is_empty() {
+        return format!("{}: -", label);
+    }
+
+    let scores: Vec<String> = scores.iter().map(u32::to_string).collect();
+    format!("{}: {}", label, scores.join(", "))
+}
+
+/// Draws `lines` centred, one below the other from `y`.
+fn draw_lines(font: Font, lines: &[String], y: f32) {
+    for (i, line) in lines.iter().enumerate() {
+        let dimensions = measure_text(line, Some(font), 22, 1.0);
+
+        draw_text_ex(
+            line,
+            (screen_width() - dimensions.width) * 0.5,
+            y + i as f32 * 28.0,
+            TextParams {
+                font,
+                font_size: 22,
+                color: LIGHTGRAY,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+struct LoseState {
+    font: Rc<RefCell<Font>>,
+    summary: Option<Summary>,
+}
+
+impl LoseState {
+    pub async fn new(font: Rc<RefCell<Font>>, summary: Option<Summary>) -> Self {
+        Self { font, summary }
+    }
+}
+
+impl State for LoseState {
+    fn draw(&self, _canvas: &Canvas) {
+        let text = match self.summary.as_ref() {
+            Some(summary) => summary.title,
+            None => "You lose the game!",
+        };
+        let dimensions = measure_text(text, Some(*self.font.borrow()), 50, 1.0);
+        let y = match self.summary {
+            Some(_) => screen_height() * 0.2,
+            None => (screen_height() - dimensions.height) * 0.5,
+        };
+
+        draw_text_ex(
+            text,
+            (screen_width() - dimensions.width) * 0.5,
+            y,
+            TextParams {
+                font: *self.font.borrow(),
+                font_size: 50,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        if let Some(summary) = self.summary.as_ref() {
+            let mut lines: Vec<String> = match summary.scores.as_slice() {
+                [score] => vec![format!("Score: {}", score)],
+                scores => scores
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, score)| format!("P{} Score: {}", slot + 1, score))
+                    .collect(),
+            };
+            lines.extend(summary.stats.lines());
+            let label = match summary.date {
+                Some(date) => {
+                    lines.push(format!("Daily {} seed {}", date, date.seed()));
+                    format!("Daily {}", date)
+                }
+                None => summary.game_mode.label().to_string(),
+            };
+            lines.push(high_scores_line(&label, &summary.high_scores));
+            if summary.new_high_score {
+                lines.push("New high score!".to_string());
+            }
+
+            draw_lines(*self.font.borrow(), &lines, y + 50.0);
+        }
+    }
+
+    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
+        let mut play = false;
+        let mut quit = false;
+        let width = screen_width();
+        let y = match self.summary {
+            Some(_) => screen_height() - 170.0,
+            None => screen_height() * 0.5 + 80.0,
+        };
+
+        widgets::Window::new(hash!(), vec2((width - 120.) * 0.5, y), vec2(120., 148.))
+            .movable(false)
+            .titlebar(false)
+            .ui(&mut root_ui(), |ui| {
+                if widgets::Button::new("Retry").size(vec2(113., 70.)).ui(ui) {
+                    play = true;
+                }
+
+                if widgets::Button::new("Quit").size(vec2(113., 70.)).ui(ui) {
+                    quit = true;
+                }
+            });
+
+        if play {
+            return Box::new(|state_manager| {
+                state_manager.switch_to(Type::Game);
+
+                true
+            });
+        }
+
+        Box::new(move |_| !quit)
+    }
+}
+
+/// Stats of every game played so far.
+struct StatsState {
+    font: Rc<RefCell<Font>>,
+    stats: Stats,
+    high_scores: HighScores,
+}
+
+impl StatsState {
+    pub fn new(font: Rc<RefCell<Font>>, stats: Stats, high_scores: HighScores) -> Self {
+        Self {
+            font,
+            stats,
+            high_scores,
+        }
+    }
+}
+
+impl State for StatsState {
+    fn draw(&self, _canvas: &Canvas) {
+        let text = "Stats";
+        let dimensions = measure_text(text, Some(*self.font.borrow()), 50, 1.0);
+        let y = screen_height() * 0.2;
+
+        draw_text_ex(
+            text,
+            (screen_width() - dimensions.width) * 0.5,
+            y,
+            TextParams {
+                font: *self.font.borrow(),
+                font_size: 50,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        let mut lines = vec![
+            format!("Games played: {}", self.stats.games),
+            format!("Best score: {}", self.stats.best_score),
+            format!("Total score: {}", self.stats.score),
+        ];
+        lines.extend(self.stats.lines());
+        lines.push(String::new());
+        lines.push("High scores".to_string());
+        for game_mode in GameMode::ALL {
+            lines.push(match game_mode {
+                GameMode::Daily => {
+                    let today = Date::today();
+                    let label = format!("Daily {}", today);
+                    high_scores_line(&label, self.high_scores.daily_table(today))
+                }
+                _ => high_scores_line(game_mode.label(), self.high_scores.table(game_mode)),
+            });
+        }
+
+        draw_lines(*self.font.borrow(), &lines, y + 50.0);
+    }
+
+    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
+        let mut back = false;
+
+        widgets::Window::new(
+            hash!(),
+            vec2((screen_width() - 120.) * 0.5, screen_height() - 100.0),
+            vec2(120., 78.),
+        )
+        .movable(false)
+        .titlebar(false)
+        .ui(&mut root_ui(), |ui| {
+            if widgets::Button::new("Back").size(vec2(113., 70.)).ui(ui) {
+                back = true;
+            }
+        });
+
+        if back {
+            return Box::new(|state_manager| {
+                state_manager.switch_to(Type::MainMenu);
+
+                true
+            });
+        }
+
+        Box::new(|_| true)
+    }
+}
+
+/// Every achievement, with those unlocked highlighted.
+struct AchievementsState {
+    font: Rc<RefCell<Font>>,
+    achievements: Achievements,
+}
+
+impl AchievementsState {
+    pub fn new(font: Rc<RefCell<Font>>, achievements: Achievements) -> Self {
+        Self { font, achievements }
+    }
+}
+
+impl State for AchievementsState {
+    fn draw(&self, _canvas: &Canvas) {
+        let font = *self.font.borrow();
+        let text = "Achievements";
+        let dimensions = measure_text(text, Some(font), 50, 1.0);
+        let y = screen_height() * 0.2;
+
+        draw_text_ex(
+            text,
+            (screen_width() - dimensions.width) * 0.5,
+            y,
+            TextParams {
+                font,
+                font_size: 50,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        for (i, achievement) in Achievement::ALL.iter().enumerate() {
+            let unlocked = self.achievements.is_unlocked(*achievement);
+            let status = match self.achievements.progress(*achievement) {
+                _ if unlocked => "Unlocked".to_string(),
+                Some((done, goal)) => format!("{}/{}", done, goal),
+                None => "Locked".to_string(),
+            };
+            let title = format!("{} - {}", achievement.name(), status);
+            let y = y + 60.0 + i as f32 * 64.0;
+
+            for (line, size, color, y) in [
+                (title.as_str(), 24, if unlocked { GOLD } else { GRAY }, y),
+                (achievement.description(), 18, LIGHTGRAY, y + 24.0),
+            ] {
+                let dimensions = measure_text(line, Some(font), size, 1.0);
+
+                draw_text_ex(
+                    line,
+                    (screen_width() - dimensions.width) * 0.5,
+                    y,
+                    TextParams {
+                        font,
+                        font_size: size,
+                        color,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+
+    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
+        let mut back = false;
+
+        widgets::Window::new(
+            hash!(),
+            vec2((screen_width() - 120.) * 0.5, screen_height() - 100.0),
+            vec2(120., 78.),
+        )
+        .movable(false)
+        .titlebar(false)
+        .ui(&mut root_ui(), |ui| {
+            if widgets::Button::new("Back").size(vec2(113., 70.)).ui(ui) {
+                back = true;
+            }
+        });
+
+        if back {
+            return Box::new(|state_manager| {
+                state_manager.switch_to(Type::MainMenu);
+
+                true
+            });
+        }
+
+        Box::new(|_| true)
+    }
+}
+
+/// Preferences, saved as soon as they change.
+struct SettingsState {
+    font: Rc<RefCell<Font>>,
+    settings: Rc<RefCell<Settings>>,
+}
+
+impl SettingsState {
+    pub fn new(font: Rc<RefCell<Font>>, settings: Rc<RefCell<Settings>>) -> Self {
+        Self { font, settings }
+    }
+}
+
+impl State for SettingsState {
+    fn draw(&self, _canvas: &Canvas) {
+        let text = "Settings";
+        let dimensions = measure_text(text, Some(*self.font.borrow()), 50, 1.0);
+
+        draw_text_ex(
+            text,
+            (screen_width() - dimensions.width) * 0.5,
+            screen_height() * 0.2,
+            TextParams {
+                font: *self.font.borrow(),
+                font_size: 50,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn update(&mut self) -> Box<dyn FnOnce(&mut StateManager) -> bool> {
+        let mut back = false;
+        let mut settings = *self.settings.borrow();
+
+        widgets::Window::new(
+            hash!(),
+            vec2(
+                (screen_width() - 220.) * 0.5,
+                (screen_height() - 550.) * 0.5,
+            ),
+            vec2(220., 550.),
+        )
+        .movable(false)
+        .titlebar(false)
+        .ui(&mut root_ui(), |ui| {
+            if widgets::Button::new(format!("Style: {}", settings.style.label()))
+                .size(vec2(213., 55.))
+                .ui(ui)
+            {
+                settings.style = settings.style.next();
+            }
+
+            if widgets::Button::new(format!("Aspect: {}", settings.aspect.label()))
+                .size(vec2(213., 55.))
+                .ui(ui)
+            {
+                settings.aspect = settings.aspect.next();
+            }
+
+            if widgets::Button::new(format!("Background: {}", settings.background.label()))
+                .size(vec2(213., 55.))
+                .ui(ui)
+            {
+                settings.background = settings.background.next();
+            }
+
+            if widgets::Button::new(format!("Fire: {}", settings.fire_mode.label()))
+                .size(vec2(213., 55.))
+                .ui(ui)
+            {
+                settings.fire_mode = settings.fire_mode.next();
+            }
+
+            let effects = &mut settings.effects;
+            for (label, enabled) in [
+                ("CRT", &mut effects.crt),
+                ("Bloom", &mut effects.bloom),
+                ("Fringes", &mut effects.chromatic),
+                ("Shake", &mut effects.shake),
+            ] {
+                let state = if *enabled { "On" } else { "Off" };
+                if widgets::Button::new(format!("{}: {}", label, state))
+                    .size(vec2(213., 55.))
+                    .ui(ui)
+                {
+                    *enabled = !*enabled;
+                }
+            }
+
+            if widgets::Button::new("Back").size(vec2(213., 55.)).ui(ui) {
+                back = true;
+            }
+        });
+
+        if settings != *self.settings.borrow() {
+            *self.settings.borrow_mut() = settings;
+            if let Err(error) = settings.save() {
+                error!("settings not saved error={}", error);
+            }
+        }
+
+        if back {
+            return Box::new(|state_manager| {
+                state_manager.switch_to(Type::MainMenu);
+
+                true
+            });
+        }
+
+        Box::new(|_| true)
     }
 }
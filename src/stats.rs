@@ -0,0 +1,106 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{entity::MeteorSize, event::Event, persist};
+
+const PATH: &str = "stats.json";
+
+/// What players did during a game, or during every game for the lifetime
+/// stats.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub games: u32,
+    /// Sum of the final scores.
+    pub score: u32,
+    pub best_score: u32,
+    pub shots_fired: u32,
+    /// Meteors destroyed by a shot.
+    pub hits: u32,
+    pub big_meteors: u32,
+    pub medium_meteors: u32,
+    pub small_meteors: u32,
+    pub hyperspace_jumps: u32,
+    pub waves_cleared: u32,
+    /// Seconds survived.
+    pub time: f32,
+}
+
+impl Stats {
+    /// Share of the shots fired which hit a meteor, between 0.0 and 1.0.
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.0;
+        }
+
+        (self.hits as f32 / self.shots_fired as f32).min(1.0)
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::ShotFired { .. } => self.shots_fired += 1,
+            Event::MeteorDestroyed { size, shot, .. } => {
+                if *shot {
+                    self.hits += 1;
+                }
+
+                match size {
+                    MeteorSize::Big => self.big_meteors += 1,
+                    MeteorSize::Medium => self.medium_meteors += 1,
+                    MeteorSize::Small => self.small_meteors += 1,
+                }
+            }
+            Event::HyperspaceJump { .. } => self.hyperspace_jumps += 1,
+            Event::PowerUpCollected { .. } | Event::ShipDestroyed { .. } => (),
+        }
+    }
+
+    /// Closes the stats of a game ending with the given scores.
+    pub fn finish(&mut self, scores: &[u32]) {
+        self.games = 1;
+        self.score = scores.iter().sum();
+        self.best_score = scores.iter().copied().max().unwrap_or_default();
+    }
+
+    /// Adds the stats of a finished `game`.
+    pub fn add(&mut self, game: &Stats) {
+        self.games += game.games;
+        self.score += game.score;
+        self.best_score = self.best_score.max(game.best_score);
+        self.shots_fired += game.shots_fired;
+        self.hits += game.hits;
+        self.big_meteors += game.big_meteors;
+        self.medium_meteors += game.medium_meteors;
+        self.small_meteors += game.small_meteors;
+        self.hyperspace_jumps += game.hyperspace_jumps;
+        self.waves_cleared += game.waves_cleared;
+        self.time += game.time;
+    }
+
+    /// Lines describing the stats, to display them.
+    pub fn lines(&self) -> Vec<String> {
+        let time = self.time as u32;
+
+        vec![
+            format!("Time: {}:{:02}", time / 60, time % 60),
+            format!("Waves cleared: {}", self.waves_cleared),
+            format!("Shots fired: {}", self.shots_fired),
+            format!("Accuracy: {:.0}%", self.accuracy() * 100.0),
+            format!(
+                "Meteors: {} big, {} medium, {} small",
+                self.big_meteors, self.medium_meteors, self.small_meteors
+            ),
+            format!("Hyperspace jumps: {}", self.hyperspace_jumps),
+        ]
+    }
+
+    /// Reads the lifetime stats, empty if they were never saved.
+    pub fn load() -> Self {
+        persist::load_json(PATH)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        persist::save_json(PATH, self)
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::event::{DeathCause, Event};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Death {
+    pub player: u8,
+    /// Seconds since the wave started.
+    pub time: f32,
+    pub cause: DeathCause,
+}
+
+/// What happened during a wave, for balancing.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct WaveStats {
+    /// Seconds since the Unix epoch, shared by the waves of a session.
+    pub session: u64,
+    pub level: u32,
+    /// Seconds spent on the wave.
+    pub time: f32,
+    pub shots_fired: u32,
+    /// Meteors destroyed by a shot.
+    pub hits: u32,
+    /// Meteors destroyed by a ship or a smart bomb.
+    pub rammed: u32,
+    pub deaths: Vec<Death>,
+    /// How the wave ended: cleared, game over or turn over.
+    pub outcome: String,
+}
+
+impl WaveStats {
+    /// Share of the shots fired which hit a meteor, between 0.0 and 1.0.
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.0;
+        }
+
+        (self.hits as f32 / self.shots_fired as f32).min(1.0)
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(flatten)]
+    wave: &'a WaveStats,
+    accuracy: f32,
+}
+
+/// Records the stats of each wave as JSON lines in a local file, enabled by
+/// setting `ASTEROIDS_TELEMETRY` to the path of the file.
+pub struct Telemetry {
+    file: Option<File>,
+    wave: WaveStats,
+}
+
+impl Telemetry {
+    pub fn from_env() -> Self {
+        let file =
+            std::env::var("ASTEROIDS_TELEMETRY").ok().and_then(|path| {
+                match OpenOptions::new().create(true).append(true).open(&path) {
+                    Ok(file) => {
+                        info!("telemetry recorded path={}", path);
+                        Some(file)
+                    }
+                    Err(error) => {
+                        warn!("telemetry disabled path={} error={}", path, error);
+                        None
+                    }
+                }
+            });
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            file,
+            wave: WaveStats {
+                session,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn start_wave(&mut self, level: u32) {
+        self.wave = WaveStats {
+            session: self.wave.session,
+            level,
+            ..Default::default()
+        };
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.wave.time += dt;
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::ShotFired { .. } => self.wave.shots_fired += 1,
+            Event::MeteorDestroyed { shot: true, .. } => self.wave.hits += 1,
+            Event::MeteorDestroyed { shot: false, .. } => self.wave.rammed += 1,
+            Event::ShipDestroyed { player, cause } => self.wave.deaths.push(Death {
+                player: *player,
+                time: self.wave.time,
+                cause: *cause,
+            }),
+            Event::PowerUpCollected { .. } | Event::HyperspaceJump { .. } => (),
+        }
+    }
+
+    /// Writes the stats of the current wave, which ended with `outcome`.
+    pub fn finish_wave(&mut self, outcome: &str) {
+        self.wave.outcome = outcome.to_string();
+        info!(
+            "wave finished level={} outcome={} time={:.1} shots={} accuracy={:.2} deaths={}",
+            self.wave.level,
+            outcome,
+            self.wave.time,
+            self.wave.shots_fired,
+            self.wave.accuracy(),
+            self.wave.deaths.len()
+        );
+
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+
+        let record = Record {
+            wave: &self.wave,
+            accuracy: self.wave.accuracy(),
+        };
+        let written = serde_json::to_string(&record)
+            .map_err(|error| error.to_string())
+            .and_then(|json| writeln!(file, "{}", json).map_err(|error| error.to_string()));
+
+        if let Err(error) = written {
+            warn!("telemetry not written error={}", error);
+        }
+    }
+}
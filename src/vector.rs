@@ -0,0 +1,260 @@
+use std::cell::Cell;
+
+use log::warn;
+use macroquad::{
+    miniquad::{BlendFactor, BlendState, Equation},
+    prelude::*,
+};
+
+/// Outline of the ship, nose up, in a square of side 1 centred on the origin.
+pub const SHIP: [Vec2; 5] = [
+    Vec2::new(0.0, -0.5),
+    Vec2::new(0.4, 0.5),
+    Vec2::new(0.25, 0.3),
+    Vec2::new(-0.25, 0.3),
+    Vec2::new(-0.4, 0.5),
+];
+
+/// Flame behind a thrusting ship, in the same square as [`SHIP`].
+pub const FLAME: [Vec2; 3] = [
+    Vec2::new(0.15, 0.3),
+    Vec2::new(0.0, 0.65),
+    Vec2::new(-0.15, 0.3),
+];
+
+/// Corners of a meteor outline.
+const METEOR_CORNERS: u64 = 11;
+
+/// Strokes drawn for each line, from the faint wide halo to the sharp core, as
+/// thickness and opacity.
+const GLOW: [(f32, f32); 3] = [(6.0, 0.12), (3.5, 0.3), (1.5, 1.0)];
+
+/// Moves the points of `shape` to `position`, scaled by `size` and rotated by
+/// `rotation` in degrees.
+pub fn transform(shape: &[Vec2], size: Vec2, rotation: f32, position: Vec2) -> Vec<Vec2> {
+    let rotation = Vec2::from_angle(rotation.to_radians());
+
+    shape
+        .iter()
+        .map(|point| position + rotation.rotate(*point * size))
+        .collect()
+}
+
+/// Jagged outline of a meteor of `radius` centred on the origin, always the
+/// same for a given `seed` so it does not draw from the game's random numbers.
+pub fn meteor(seed: u64, radius: f32) -> Vec<Vec2> {
+    (0..METEOR_CORNERS)
+        .map(|corner| {
+            // Cheap hash of the seed and the corner, between 0.0 and 1.0
+            let mut hash = (seed ^ (corner << 32)).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            hash ^= hash >> 29;
+            let jag = (hash % 1000) as f32 / 1000.0;
+
+            let angle = corner as f32 / METEOR_CORNERS as f32 * std::f32::consts::TAU;
+            Vec2::from_angle(angle) * radius * (0.7 + 0.3 * jag)
+        })
+        .collect()
+}
+
+/// Draws the lines between `points` with a phosphor glow, back to the first
+/// point if `closed`.
+pub fn draw_outline(points: &[Vec2], closed: bool, color: Color) {
+    let segments = points.windows(2).map(|pair| (pair[0], pair[1]));
+    let closing = match (closed, points.first(), points.last()) {
+        (true, Some(first), Some(last)) => Some((*last, *first)),
+        _ => None,
+    };
+    let segments: Vec<(Vec2, Vec2)> = segments.chain(closing).collect();
+
+    for (thickness, alpha) in GLOW {
+        let color = Color::new(color.r, color.g, color.b, color.a * alpha);
+        for (start, end) in segments.iter() {
+            draw_line(start.x, start.y, end.x, end.y, thickness, color);
+        }
+    }
+}
+
+/// Draws a circle of `radius` with a phosphor glow.
+pub fn draw_circle_outline(center: Vec2, radius: f32, color: Color) {
+    for (thickness, alpha) in GLOW {
+        let color = Color::new(color.r, color.g, color.b, color.a * alpha);
+        draw_circle_lines(center.x, center.y, radius, thickness, color);
+    }
+}
+
+/// Phosphor glow of the vector style: the lines are drawn on a texture of
+/// their own, which is then added to the frame through a shader lighting a
+/// wide halo around them.
+pub struct Phosphor {
+    /// None when the shader could not be built, the lines are then drawn
+    /// without their halo.
+    material: Option<Material>,
+    /// The size of the screen, made again when the window is resized.
+    target: Cell<Option<RenderTarget>>,
+    /// Whether lines are being drawn on the target, between `begin` and
+    /// `end`.
+    drawing: Cell<bool>,
+}
+
+impl Default for Phosphor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Phosphor {
+    pub fn new() -> Self {
+        let material = load_material(
+            VERTEX_SHADER,
+            PHOSPHOR_SHADER,
+            MaterialParams {
+                uniforms: vec![("resolution".to_string(), UniformType::Float2)],
+                // Lit on top of what is already there, like on a CRT
+                pipeline_params: PipelineParams {
+                    color_blend: Some(BlendState::new(
+                        Equation::Add,
+                        BlendFactor::One,
+                        BlendFactor::One,
+                    )),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        let material = match material {
+            Ok(material) => Some(material),
+            Err(error) => {
+                warn!("phosphor glow disabled error={:?}", error);
+                None
+            }
+        };
+
+        Self {
+            material,
+            target: Cell::new(None),
+            drawing: Cell::new(false),
+        }
+    }
+
+    /// Starts drawing lines through `camera`, on a texture of their own if
+    /// the glow works.
+    pub fn begin(&self, camera: &Camera2D) {
+        let target = self.material.and_then(|_| self.target());
+        self.drawing.set(target.is_some());
+
+        let Some(target) = target else {
+            set_camera(camera);
+            return;
+        };
+
+        // The lines alone, on black
+        set_camera(&Camera2D {
+            render_target: Some(target),
+            ..*camera
+        });
+        clear_background(BLACK);
+    }
+
+    /// Adds the lines drawn since `begin` to the frame, with their halo, and
+    /// goes back to drawing through `camera`.
+    pub fn end(&self, camera: &Camera2D) {
+        let (Some(material), Some(target), true) = (
+            self.material,
+            self.target.get(),
+            self.drawing.replace(false),
+        ) else {
+            return;
+        };
+
+        // Back on the frame, in screen pixels
+        let (width, height) = (screen_width(), screen_height());
+        set_camera(&Camera2D {
+            render_target: camera.render_target,
+            ..Camera2D::from_display_rect(Rect::new(0.0, 0.0, width, height))
+        });
+        material.set_uniform("resolution", vec2(width, height));
+        gl_use_material(material);
+        draw_texture_ex(
+            target.texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(width, height)),
+                // Render targets are stored upside down
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+        set_camera(camera);
+    }
+
+    fn target(&self) -> Option<RenderTarget> {
+        let (width, height) = (screen_width() as u32, screen_height() as u32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        if let Some(target) = self.target.get() {
+            if target.texture.width() as u32 == width && target.texture.height() as u32 == height {
+                return Some(target);
+            }
+
+            target.delete();
+        }
+
+        let target = render_target(width, height);
+        target.texture.set_filter(FilterMode::Linear);
+        self.target.set(Some(target));
+
+        Some(target)
+    }
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+const PHOSPHOR_SHADER: &str = r#"#version 100
+precision mediump float;
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform sampler2D Texture;
+uniform vec2 resolution;
+
+void main() {
+    vec3 result = texture2D(Texture, uv).rgb;
+
+    // Wide halo of the thin lines, lit however dim they are
+    vec2 texel = 1.0 / resolution;
+    vec3 halo = vec3(0.0);
+    for (int x = -3; x <= 3; x++) {
+        for (int y = -3; y <= 3; y++) {
+            vec2 offset = vec2(float(x), float(y)) * texel * 2.5;
+            float falloff = 1.0 - length(vec2(float(x), float(y))) / 4.5;
+            halo += texture2D(Texture, uv + offset).rgb * max(falloff, 0.0);
+        }
+    }
+    result += halo / 49.0 * 1.8;
+
+    gl_FragColor = vec4(result, 1.0) * color;
+}
+"#;
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+/// How the fire key triggers shots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FireMode {
+    /// Keep firing while the key is held down.
+    #[default]
+    Auto,
+    /// Fire once per key press.
+    Tap,
+}
+
+impl FireMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FireMode::Auto => "Hold",
+            FireMode::Tap => "Tap",
+        }
+    }
+
+    pub fn next(&self) -> FireMode {
+        match self {
+            FireMode::Auto => FireMode::Tap,
+            FireMode::Tap => FireMode::Auto,
+        }
+    }
+}
+
+/// Tuning of the shots fired by a ship.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weapon {
+    /// Speed of a shot relative to the ship, in pixels per second.
+    pub speed: f32,
+    /// Time in seconds before a shot vanishes.
+    pub lifetime: f32,
+    /// Delay between two shots when holding the fire key.
+    pub auto_cooldown: f32,
+    /// Delay between two shots when tapping the fire key.
+    pub tap_cooldown: f32,
+    /// Maximum number of shots of a ship on screen at once.
+    pub max_shots: u8,
+    pub fire_mode: FireMode,
+    /// Number of shots fired at once, fanned out by `spread_angle` degrees.
+    pub spread_shots: u8,
+    pub spread_angle: f32,
+    /// Shots go through what they hit instead of vanishing.
+    pub piercing: bool,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            speed: 500.0,
+            lifetime: 0.5,
+            auto_cooldown: 0.5,
+            tap_cooldown: 0.1,
+            max_shots: 4,
+            fire_mode: FireMode::default(),
+            spread_shots: 1,
+            spread_angle: 15.0,
+            piercing: false,
+        }
+    }
+}
+
+impl Weapon {
+    pub fn cooldown(&self) -> f32 {
+        match self.fire_mode {
+            FireMode::Auto => self.auto_cooldown,
+            FireMode::Tap => self.tap_cooldown,
+        }
+    }
+
+    /// Rotation offsets of the shots fired at once, centred on the ship nose.
+    pub fn spread(&self) -> Vec<f32> {
+        let shots = self.spread_shots.max(1);
+        let first = -f32::from(shots - 1) * self.spread_angle / 2.0;
+
+        (0..shots)
+            .map(|i| first + i as f32 * self.spread_angle)
+            .collect()
+    }
+}
@@ -1,10 +1,31 @@
-use macroquad::window::{screen_height, screen_width};
+use std::{
+    cell::RefCell,
+    fmt::{self, Write},
+    rc::Rc,
+};
 
-use crate::entity::{self, Entity};
+use macroquad::prelude::{rand, Vec2};
+
+use crate::{
+    arena,
+    assets_manager::AssetManager,
+    collision,
+    entity::{Entity, EntitySave, PowerUp, Snapshot, Type},
+    event::Event,
+    layer::Layer,
+    physics,
+    power_up::DropTable,
+    settings::RenderStyle,
+};
 
 pub struct World {
     pub entities: Vec<Box<dyn Entity>>,
     entities_tmp: Vec<Box<dyn Entity>>,
+    events: Vec<Event>,
+    drop_table: DropTable,
+    /// Size of the arena in virtual pixels.
+    bounds: Vec2,
+    next_id: u64,
 }
 
 impl World {
@@ -12,24 +33,155 @@ impl World {
         Self {
             entities: Vec::new(),
             entities_tmp: Vec::new(),
+            events: Vec::new(),
+            drop_table: DropTable::default(),
+            bounds: arena::SIZE,
+            next_id: 1,
+        }
+    }
+
+    /// Changes the size of the arena, which every peer of an online game must
+    /// share.
+    pub fn set_bounds(&mut self, bounds: Vec2) {
+        self.bounds = bounds;
+    }
+
+    pub fn bounds(&self) -> Vec2 {
+        self.bounds
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn set_drop_table(&mut self, drop_table: DropTable) {
+        self.drop_table = drop_table;
+    }
+
+    /// Leaves a power-up behind a destroyed meteor or saucer, if the drop
+    /// table says so.
+    pub fn drop_power_up(&mut self, assets: Rc<RefCell<AssetManager>>, position: Vec2) {
+        if let Some(kind) = self.drop_table.roll() {
+            self.add(PowerUp::new(
+                assets,
+                kind,
+                position,
+                self.drop_table.lifetime,
+            ));
         }
     }
 
+    /// Whether no meteor nor saucer is closer than `radius` to `position`.
+    pub fn is_clear(&self, position: Vec2, radius: f32) -> bool {
+        self.entities
+            .iter()
+            .chain(self.entities_tmp.iter())
+            .filter(|entity| matches!(entity.get_type(), Type::Meteor(_) | Type::Enemy))
+            .all(|entity| {
+                let (center, collider) = collision::collider(entity.get_data());
+
+                collision::wrapped_delta(center, position, self.bounds()).length()
+                    > radius + collider
+            })
+    }
+
+    /// Picks a random position on screen for an entity of `size`, trying to
+    /// keep it `radius` away from meteors and saucers.
+    pub fn find_clear_position(&self, size: Vec2, radius: f32) -> Vec2 {
+        let bounds = self.bounds();
+        let random_position = || Vec2 {
+            x: rand::gen_range(size.x / 2.0, bounds.x - size.x / 2.0),
+            y: rand::gen_range(size.y / 2.0, bounds.y - size.y / 2.0),
+        };
+
+        let mut position = random_position();
+        for _ in 0..32 {
+            if self.is_clear(position, radius) {
+                break;
+            }
+
+            position = random_position();
+        }
+
+        position
+    }
+
+    /// Destroys every entity whose type matches, as if it had been hit by
+    /// `by`.
+    pub fn destroy_where(&mut self, predicate: impl Fn(&Type) -> bool, by: &Type) {
+        let mut destroys = vec![];
+        for entity in self.entities.iter_mut() {
+            if entity.is_alive() && predicate(&entity.get_type()) {
+                entity.on_hit(by);
+                if let Some(destroy) = entity.on_destroy() {
+                    destroys.push(destroy);
+                }
+            }
+        }
+
+        for destroy in destroys.into_iter() {
+            destroy(self);
+        }
+    }
+
+    /// Removes every entity whose type matches, without destroying them.
+    pub fn remove_where(&mut self, predicate: impl Fn(&Type) -> bool) {
+        self.entities
+            .retain(|entity| !predicate(&entity.get_type()));
+        self.entities_tmp
+            .retain(|entity| !predicate(&entity.get_type()));
+    }
+
     pub fn size(&self) -> usize {
         self.entities.len() + self.entities_tmp.len()
     }
 
-    pub fn add(&mut self, entity: impl Entity + 'static) {
+    /// Number of entities, including the ones added this frame, whose type
+    /// matches.
+    pub fn count(&self, predicate: impl Fn(&Type) -> bool) -> usize {
+        self.entities
+            .iter()
+            .chain(self.entities_tmp.iter())
+            .filter(|entity| predicate(&entity.get_type()))
+            .count()
+    }
+
+    /// Positions of the entities, including the ones added this frame, whose
+    /// type matches.
+    pub fn positions_of(&self, predicate: impl Fn(&Type) -> bool) -> Vec<Vec2> {
+        self.entities
+            .iter()
+            .chain(self.entities_tmp.iter())
+            .filter(|entity| predicate(&entity.get_type()))
+            .map(|entity| entity.get_data().position)
+            .collect()
+    }
+
+    pub fn add(&mut self, mut entity: impl Entity + 'static) {
+        entity.get_data_mut().set_id(self.next_id);
+        self.next_id += 1;
         self.entities_tmp.push(Box::new(entity));
     }
 
-    pub fn update(&mut self, dt: f32) -> u32 {
+    pub fn update(&mut self, dt: f32) {
         if !self.entities_tmp.is_empty() {
             self.entities.append(&mut self.entities_tmp);
         }
 
         let mut updates = vec![];
         for entity in self.entities.iter_mut() {
+            let events = match entity.get_data_mut().animation.as_mut() {
+                Some(animation) => animation.update(dt),
+                None => vec![],
+            };
+            for event in events.iter() {
+                entity.on_animation_event(event);
+            }
+
             if let Some(update) = entity.update(dt) {
                 updates.push(update);
             }
@@ -40,85 +192,141 @@ impl World {
         }
 
         // Handle if entity is outside bounds
+        let bounds = self.bounds();
         for entity in self.entities.iter_mut() {
-            let mut position = entity.get_data().position;
-
-            if position.x < -entity.get_data().texture.borrow().width() {
-                position.x = screen_width();
-                // position.y = screen_height() - position.y;
-            } else if position.x > screen_width() {
-                position.x = -(entity.get_data().texture.borrow().width());
-                // position.y = screen_height() - position.y;
-            }
-
-            if position.y < -entity.get_data().texture.borrow().height() {
-                position.y = screen_height();
-            } else if position.y > screen_height() {
-                position.y = -entity.get_data().texture.borrow().height();
-            }
-
+            let position = physics::wrap(entity.get_data().position, bounds);
             entity.set_position(position);
         }
 
         let mut a = vec![];
         for (i, entity_i) in self.entities.iter().enumerate() {
             for (j, entity_j) in self.entities.iter().skip(i + 1).enumerate() {
-                if entity_i.is_alive() && entity_i.is_collide(entity_j.as_ref()) {
-                    a.push(i);
+                if entity_i.is_alive() && entity_i.is_collide(entity_j.as_ref(), bounds) {
+                    a.push((i, entity_j.get_type()));
                 }
 
-                if entity_j.is_alive() && entity_j.is_collide(entity_i.as_ref()) {
-                    a.push(j + i + 1);
+                if entity_j.is_alive() && entity_j.is_collide(entity_i.as_ref(), bounds) {
+                    a.push((j + i + 1, entity_i.get_type()));
                 }
             }
         }
 
-        for index in a.into_iter() {
+        for (index, by) in a.into_iter() {
             if let Some(entity) = self.entities.get_mut(index) {
+                entity.on_hit(&by);
                 if let Some(destroy) = entity.on_destroy() {
                     destroy(self);
                 }
             }
         }
 
-        let mut remove_entities_index = vec![];
+        self.entities.retain(|entity| entity.is_alive());
+    }
 
-        for (index, entity) in self.entities.iter_mut().enumerate() {
-            if !entity.is_alive() {
-                remove_entities_index.push(index);
-            }
-        }
+    /// State of the entities to write in a save file.
+    pub fn save(&self) -> Vec<EntitySave> {
+        self.entities
+            .iter()
+            .chain(self.entities_tmp.iter())
+            .filter(|entity| entity.is_alive())
+            .filter_map(|entity| entity.save())
+            .collect()
+    }
 
-        let mut pts = 0;
-        for id in remove_entities_index.into_iter() {
-            if let Some(entity) = self.entities.get(id) {
-                if let entity::Type::Meteor(p) = entity.get_type() {
-                    pts += p;
-                }
-                self.entities.remove(id);
-            }
+    /// Plain data view of every entity, in update order.
+    pub fn snapshot(&self) -> Vec<Snapshot> {
+        self.entities
+            .iter()
+            .chain(self.entities_tmp.iter())
+            .filter(|entity| entity.is_alive())
+            .map(|entity| entity.snapshot())
+            .collect()
+    }
+
+    /// Dumps the entities to JSON, to inspect the world or rebuild it later.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.snapshot())
+    }
+
+    /// Reads back the entities dumped by `to_json`.
+    pub fn snapshot_from_json(json: &str) -> serde_json::Result<Vec<Snapshot>> {
+        serde_json::from_str(json)
+    }
+
+    /// Hash of the full snapshot of every entity, for peers to check their
+    /// worlds did not diverge. Floats print exactly, and the hash does not
+    /// change between builds nor platforms.
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = Fnv::default();
+        for snapshot in self.snapshot() {
+            // Writing to a hasher cannot fail
+            let _ = write!(hasher, "{:?}", snapshot);
         }
 
-        pts
+        hasher.0
     }
 
     pub fn clear(&mut self) {
         self.entities.clear();
     }
 
-    pub fn draw(&self) {
-        for entity in self.entities.iter() {
-            entity.draw();
+    /// Draws the entities of `layer` in `style` by z-order, along with ghosts
+    /// on the opposite side of the edges they straddle.
+    pub fn draw_layer(&self, layer: Layer, style: RenderStyle) {
+        let bounds = self.bounds();
+
+        // Sprites of the atlas share its texture and go to the GPU in a single
+        // batch, the others are grouped by texture. The sort is stable so
+        // ties keep the order they were added in
+        let mut entities: Vec<&dyn Entity> = self
+            .entities
+            .iter()
+            .map(|entity| entity.as_ref())
+            .filter(|entity| entity.layer() == layer)
+            .collect();
+        entities.sort_by_key(|entity| {
+            let texture = entity
+                .get_data()
+                .sprite
+                .texture
+                .raw_miniquad_texture_handle();
+            (entity.z_order(), texture.gl_internal_id())
+        });
+
+        for entity in entities {
+            let data = entity.get_data();
+            // Half the diagonal covers the sprite whatever its rotation
+            let radius = data.sprite.size().length() / 2.0;
+
+            let draw = |position| match style {
+                RenderStyle::Sprites => entity.draw(position),
+                RenderStyle::Vector => entity.draw_vector(position),
+            };
+
+            draw(data.position);
+            for offset in physics::ghosts(data.position, radius, bounds) {
+                draw(data.position + offset);
+            }
         }
     }
+}
+
+/// 64-bit FNV-1a hash of the text written to it.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
 
-    // pub fn is_collide(&self, other: impl Entity) -> bool {
-    //     for entity in self.entities.iter() {
-    //         if other.is_collide(entity.as_ref()) {
-    //             return true;
-    //         }
-    //     }
+impl Write for Fnv {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        for byte in text.bytes() {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
 
-    //     false
-    // }
+        Ok(())
+    }
 }
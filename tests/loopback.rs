@@ -0,0 +1,200 @@
+//! Runs two peers of an online game over UDP on 127.0.0.1 in one process,
+//! driven by scripted inputs, and checks their simulations never diverge.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use asteroids::{
+    assets_manager::AssetManager,
+    entity::{Entity, Meteor, MeteorSize, Player, ShipConfig},
+    input::ShipInput,
+    net::{self, Lockstep},
+    power_up::PowerUps,
+    world::World,
+};
+use macroquad::prelude::{rand, vec2, Vec2};
+
+const FRAMES: u32 = 1200;
+
+const ARENA: Vec2 = Vec2::new(1280.0, 720.0);
+
+/// One side of the session, with a world of its own run without a window.
+struct Peer {
+    session: Lockstep,
+    world: World,
+    inputs: [Rc<RefCell<ShipInput>>; 2],
+    checksums: Vec<u64>,
+}
+
+impl Peer {
+    fn new(session: Lockstep) -> Self {
+        let assets = Rc::new(RefCell::new(AssetManager::headless()));
+        let config = ShipConfig {
+            friendly_fire: session.friendly_fire,
+            arena: session.arena,
+            ..Default::default()
+        };
+        let inputs = [(); 2].map(|_| Rc::new(RefCell::new(ShipInput::default())));
+
+        let mut world = World::new();
+        world.set_bounds(session.arena);
+        for (slot, input) in inputs.iter().enumerate() {
+            let mut ship = pollster::block_on(Player::new(
+                assets.clone(),
+                slot as u8,
+                input.clone(),
+                Rc::new(RefCell::new(3)),
+                Rc::new(RefCell::new(PowerUps::default())),
+                &config,
+            ));
+            ship.set_position(vec2(
+                ARENA.x / 2.0 + (slot as f32 - 0.5) * 100.0,
+                ARENA.y / 2.0,
+            ));
+            world.add(ship);
+        }
+
+        // Laid out by the shared seed, like the first wave of a game
+        net::reseed(session.seed().unwrap(), 0);
+        for size in [MeteorSize::Big, MeteorSize::Big, MeteorSize::Medium] {
+            let mut meteor = pollster::block_on(Meteor::new(assets.clone(), size));
+            meteor.set_position(vec2(
+                rand::gen_range(0.0, ARENA.x),
+                rand::gen_range(0.0, 100.0),
+            ));
+            world.add(meteor);
+        }
+
+        Self {
+            session,
+            world,
+            inputs,
+            checksums: vec![],
+        }
+    }
+
+    /// Advances by one frame if the inputs of both ships are known.
+    fn step(&mut self) -> bool {
+        self.session.poll();
+        self.session
+            .push_local(scripted(self.session.local_slot(), self.session.frame()));
+
+        let Some(inputs) = self.session.inputs() else {
+            return false;
+        };
+
+        net::reseed(self.session.seed().unwrap(), self.session.frame());
+        for (shared, input) in self.inputs.iter().zip(inputs) {
+            *shared.borrow_mut() = input;
+        }
+        self.world.update(net::FIXED_DT);
+        self.world.drain_events();
+
+        let checksum = self.world.checksum();
+        self.checksums.push(checksum);
+        self.session.advance(checksum);
+
+        true
+    }
+}
+
+/// Input of the ship in `slot` on `frame`, different for each ship.
+fn scripted(slot: u8, frame: u32) -> ShipInput {
+    let frame = frame + u32::from(slot) * 37;
+
+    ShipInput {
+        thrust: frame % 120 < 40,
+        turn: if frame % 90 < 30 { 1.0 } else { 0.0 },
+        fire: frame % 40 < 20,
+        fire_pressed: frame % 20 == 0,
+        escape: frame % 400 == 300,
+    }
+}
+
+#[test]
+fn peers_never_diverge() {
+    let deadline = Instant::now() + Duration::from_secs(30);
+
+    let mut host = Lockstep::host(0, ARENA, true).unwrap();
+    let port = host.local_addr().unwrap().port();
+    let mut client = Lockstep::join(("127.0.0.1", port)).unwrap();
+
+    while !(host.is_connected() && client.is_connected()) {
+        assert!(Instant::now() < deadline, "peers never connected");
+        host.poll();
+        client.poll();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(client.seed(), host.seed());
+    assert_eq!(client.arena, ARENA);
+    assert!(client.friendly_fire);
+
+    let mut peers = [Peer::new(host), Peer::new(client)];
+    while peers.iter().any(|peer| peer.session.frame() < FRAMES) {
+        assert!(Instant::now() < deadline, "peers stalled");
+
+        let mut advanced = false;
+        for peer in peers.iter_mut() {
+            if peer.session.frame() < FRAMES {
+                advanced |= peer.step();
+            }
+        }
+
+        if !advanced {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    let [host, client] = &peers;
+    for (frame, (a, b)) in host
+        .checksums
+        .iter()
+        .zip(client.checksums.iter())
+        .enumerate()
+    {
+        assert_eq!(a, b, "peers diverged at frame {}", frame);
+    }
+    assert_eq!(host.checksums.len(), FRAMES as usize);
+    // The ships did move, so matching checksums mean something
+    assert_ne!(host.checksums.first(), host.checksums.last());
+    assert_eq!(host.world.checksum(), client.world.checksum());
+    assert_eq!(host.session.desync(), None);
+    assert_eq!(client.session.desync(), None);
+}
+
+#[test]
+fn strangers_cannot_take_over_a_session() {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    let mut host = Lockstep::host(0, ARENA, false).unwrap();
+    let port = host.local_addr().unwrap().port();
+    let mut client = Lockstep::join(("127.0.0.1", port)).unwrap();
+    while !(host.is_connected() && client.is_connected()) {
+        assert!(Instant::now() < deadline, "peers never connected");
+        host.poll();
+        client.poll();
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    // Says hello to the host, and sends inputs for the frames to come
+    let mut stranger = Lockstep::join(("127.0.0.1", port)).unwrap();
+    for _ in 0..100 {
+        stranger.poll();
+        stranger.push_local(ShipInput::default());
+        host.poll();
+        client.poll();
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert!(!stranger.is_connected());
+    // Past the frames nobody moves, the host still waits for its only peer
+    host.push_local(ShipInput::default());
+    while host.frame() < 3 {
+        host.advance(0);
+    }
+    assert_eq!(host.inputs(), None);
+}